@@ -1,18 +1,61 @@
+use std::rc::Rc;
 
+use crate::{bigint::BigInt, span::Span};
+
+/// A node in the syntax tree together with the source it was parsed from.
+///
+/// Two statements compare equal when their kinds are equal, their spans are
+/// ignored so that trees can be compared regardless of formatting.
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl PartialEq for Stmt {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl From<StmtKind> for Stmt {
+    fn from(kind: StmtKind) -> Self {
+        Self::new(kind, Span::default())
+    }
+}
 
 #[derive(Debug, PartialEq, Clone)]
-pub enum Stmt {
+pub enum StmtKind {
     Program(Program),
     ObjectLiteral(Vec<Property>),
-    NumericLiteral(String),
+    ArrayLiteral(Vec<Stmt>),
+    IntegerLiteral(i64),
+    BigIntLiteral(BigInt),
+    FloatLiteral(f64),
+    StringLiteral(String),
+    TemplateLiteral(Vec<TemplatePart>),
     Identifier(String),
-    Comment(String),
+    /// `@name`, a reference to the binding of a variable.
+    Reference(String),
+    /// The parameters and body are shared with every function value created
+    /// from the declaration.
     FnDeclaration {
         name: String,
-        parameters: Vec<String>,
-        body: Vec<Stmt>,
+        parameters: Rc<[Parameter]>,
+        body: Rc<[Stmt]>,
         is_const: bool,
     },
+    /// An anonymous function, `fn(x) { x + 1 }`.
+    FnExpr {
+        parameters: Rc<[Parameter]>,
+        body: Rc<[Stmt]>,
+    },
     VarDeclaration {
         constant: bool,
         identifier: String,
@@ -22,13 +65,19 @@ pub enum Stmt {
         assignee: Box<Stmt>,
         value: Box<Stmt>,
     },
+    /// `a += b`, `operator` is the binary operator applied, `+`.
+    CompoundAssignment {
+        assignee: Box<Stmt>,
+        value: Box<Stmt>,
+        operator: String,
+    },
     MemberExpr {
         object: Box<Stmt>,
         property: Box<Stmt>,
         computed: bool,
     },
     CallExpr {
-        args: Vec<Stmt>,
+        args: Vec<Argument>,
         caller: Box<Stmt>,
     },
     BinaryExpr {
@@ -36,10 +85,95 @@ pub enum Stmt {
         right: Box<Stmt>,
         operator: String,
     },
-    UniaryExpr,
+    /// `&&` and `||`, the right side is only evaluated when needed.
+    LogicalExpr {
+        left: Box<Stmt>,
+        right: Box<Stmt>,
+        operator: String,
+    },
+    UnaryExpr {
+        operator: String,
+        operand: Box<Stmt>,
+    },
+    /// `else_branch` is either another `IfExpr` or a `Block`.
+    IfExpr {
+        condition: Box<Stmt>,
+        then_branch: Vec<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+    },
+    Block(Vec<Stmt>),
+    WhileLoop {
+        condition: Box<Stmt>,
+        body: Vec<Stmt>,
+    },
+    Loop(Vec<Stmt>),
+    ForLoop {
+        variable: String,
+        iterable: Box<Stmt>,
+        body: Vec<Stmt>,
+    },
+    RangeExpr {
+        start: Box<Stmt>,
+        end: Box<Stmt>,
+        inclusive: bool,
+    },
+    Break(Option<Box<Stmt>>),
+    Continue,
+    Return(Option<Box<Stmt>>),
     FunctionDeclaration,
 }
 
+impl StmtKind {
+    /// Human readable name of the node, used in error messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StmtKind::Program(_) => "program",
+            StmtKind::ObjectLiteral(_) => "object literal",
+            StmtKind::ArrayLiteral(_) => "array literal",
+            StmtKind::IntegerLiteral(_) | StmtKind::BigIntLiteral(_) => "integer literal",
+            StmtKind::FloatLiteral(_) => "float literal",
+            StmtKind::StringLiteral(_) => "string literal",
+            StmtKind::TemplateLiteral(_) => "string literal",
+            StmtKind::Identifier(_) => "identifier",
+            StmtKind::Reference(_) => "reference",
+            StmtKind::FnDeclaration { .. } => "function declaration",
+            StmtKind::FnExpr { .. } => "function expression",
+            StmtKind::VarDeclaration { .. } => "variable declaration",
+            StmtKind::AssignmentExpr { .. } => "assignment",
+            StmtKind::CompoundAssignment { .. } => "compound assignment",
+            StmtKind::MemberExpr { .. } => "member expression",
+            StmtKind::CallExpr { .. } => "call expression",
+            StmtKind::BinaryExpr { .. } => "binary expression",
+            StmtKind::LogicalExpr { .. } => "logical expression",
+            StmtKind::UnaryExpr { .. } => "unary expression",
+            StmtKind::IfExpr { .. } => "if expression",
+            StmtKind::Block(_) => "block",
+            StmtKind::WhileLoop { .. } => "while loop",
+            StmtKind::Loop(_) => "loop",
+            StmtKind::ForLoop { .. } => "for loop",
+            StmtKind::RangeExpr { .. } => "range",
+            StmtKind::Break(_) => "break",
+            StmtKind::Continue => "continue",
+            StmtKind::Return(_) => "return",
+            StmtKind::FunctionDeclaration => "function declaration",
+        }
+    }
+
+    /// Whether the statement ends in a block, such statements do not need to
+    /// be followed by a semicolon.
+    pub fn ends_with_block(&self) -> bool {
+        matches!(
+            self,
+            StmtKind::FnDeclaration { .. }
+                | StmtKind::IfExpr { .. }
+                | StmtKind::Block(_)
+                | StmtKind::WhileLoop { .. }
+                | StmtKind::Loop(_)
+                | StmtKind::ForLoop { .. }
+        )
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Program {
     pub body: Vec<Stmt>,
@@ -48,6 +182,35 @@ pub struct Program {
 #[derive(Debug, PartialEq, Clone)]
 pub struct Expr {}
 
+/// A piece of an interpolated string, `"hello {name}"`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TemplatePart {
+    Literal(String),
+    Expr(Stmt),
+}
+
+/// `x`, `x = default` or `...rest` in a function signature.
+///
+/// The default is evaluated on each call that leaves the parameter out, after
+/// the parameters before it have been bound. A rest parameter comes last and
+/// collects the remaining arguments into an array.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub default: Option<Stmt>,
+    pub rest: bool,
+}
+
+/// An argument in a call, `f(x)`, `f(...list)` or `f(name: x)`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Argument {
+    Positional(Stmt),
+    /// Binds the parameter with the given name, follows all positional ones.
+    Named(String, Stmt),
+    /// Passes each element of an iterable as a separate argument.
+    Spread(Stmt),
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Property {
     pub key: String,
@@ -0,0 +1,364 @@
+use std::cmp::Ordering;
+
+/// An arbitrary-precision signed integer.
+///
+/// The magnitude is stored as little-endian base 2^32 limbs without trailing
+/// zeros, zero is an empty magnitude and never negative.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self {
+            negative: false,
+            magnitude: vec![],
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    /// Parses digits in the given radix with an optional leading `-`.
+    pub fn from_str_radix(src: &str, radix: u32) -> Option<Self> {
+        let (negative, digits) = match src.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, src),
+        };
+
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut magnitude = vec![];
+        for c in digits.chars() {
+            let digit = c.to_digit(radix)?;
+            mul_small_add(&mut magnitude, radix, digit);
+        }
+
+        Some(Self::from_parts(negative, magnitude))
+    }
+
+    /// Converts back into an `i64` if the value is within its range.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0u64, |acc, limb| (acc << 32) | *limb as u64);
+
+        if self.negative {
+            if magnitude == i64::MIN.unsigned_abs() {
+                Some(i64::MIN)
+            } else {
+                i64::try_from(magnitude).ok().map(|m| -m)
+            }
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |acc, limb| acc * 4294967296.0 + *limb as f64);
+
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    pub fn add(&self, rhs: &BigInt) -> BigInt {
+        if self.negative == rhs.negative {
+            return Self::from_parts(
+                self.negative,
+                add_magnitudes(&self.magnitude, &rhs.magnitude),
+            );
+        }
+
+        match cmp_magnitudes(&self.magnitude, &rhs.magnitude) {
+            Ordering::Less => Self::from_parts(
+                rhs.negative,
+                sub_magnitudes(&rhs.magnitude, &self.magnitude),
+            ),
+            _ => Self::from_parts(
+                self.negative,
+                sub_magnitudes(&self.magnitude, &rhs.magnitude),
+            ),
+        }
+    }
+
+    pub fn sub(&self, rhs: &BigInt) -> BigInt {
+        self.add(&rhs.neg())
+    }
+
+    pub fn mul(&self, rhs: &BigInt) -> BigInt {
+        let mut magnitude = vec![0u32; self.magnitude.len() + rhs.magnitude.len()];
+
+        for (i, a) in self.magnitude.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in rhs.magnitude.iter().enumerate() {
+                let product = *a as u64 * *b as u64 + magnitude[i + j] as u64 + carry;
+                magnitude[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            magnitude[i + rhs.magnitude.len()] = carry as u32;
+        }
+
+        Self::from_parts(self.negative != rhs.negative, magnitude)
+    }
+
+    /// Truncating division and remainder, matching the semantics of `i64`.
+    /// Returns `None` when dividing by zero.
+    pub fn div_rem(&self, rhs: &BigInt) -> Option<(BigInt, BigInt)> {
+        if rhs.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = div_rem_magnitudes(&self.magnitude, &rhs.magnitude);
+
+        Some((
+            Self::from_parts(self.negative != rhs.negative, quotient),
+            Self::from_parts(self.negative, remainder),
+        ))
+    }
+
+    pub fn neg(&self) -> BigInt {
+        Self::from_parts(!self.negative, self.magnitude.clone())
+    }
+
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+
+        Self {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        Self::from_parts(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitudes(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitudes(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl std::fmt::Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Peel off chunks of nine decimal digits, least significant first
+        let mut chunks = vec![];
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            chunks.push(div_small(&mut magnitude, 1_000_000_000));
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{first}")?;
+        }
+        for chunk in chunks {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+fn cmp_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    result.push(carry as u32);
+
+    result
+}
+
+/// Computes `a - b`, requires `a >= b`.
+fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+
+    for (i, limb) in a.iter().enumerate() {
+        let mut difference = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if difference < 0 {
+            difference += 1 << 32;
+            borrow = 1;
+        }
+        result.push(difference as u32);
+    }
+
+    result
+}
+
+fn mul_small_add(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+
+    for limb in magnitude.iter_mut() {
+        let product = *limb as u64 * factor as u64 + carry;
+        *limb = product as u32;
+        carry = product >> 32;
+    }
+
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+/// Divides `magnitude` in place, returning the remainder.
+fn div_small(magnitude: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+
+    for limb in magnitude.iter_mut().rev() {
+        let current = (remainder << 32) | *limb as u64;
+        *limb = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+
+    remainder as u32
+}
+
+/// Schoolbook binary long division, `b` must not be zero.
+fn div_rem_magnitudes(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [divisor] = b {
+        let mut quotient = a.to_vec();
+        let remainder = div_small(&mut quotient, *divisor);
+        return (quotient, vec![remainder]);
+    }
+
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = vec![];
+
+    for bit in (0..a.len() * 32).rev() {
+        // remainder = remainder << 1 | next bit of a
+        let next = (a[bit / 32] >> (bit % 32)) & 1;
+        mul_small_add(&mut remainder, 2, next);
+
+        if cmp_magnitudes(&remainder, b) != Ordering::Less {
+            remainder = sub_magnitudes(&remainder, b);
+            while remainder.last() == Some(&0) {
+                remainder.pop();
+            }
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(src: &str) -> BigInt {
+        BigInt::from_str_radix(src, 10).unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for src in [
+            "0",
+            "1",
+            "-42",
+            "4294967296",
+            "-123456789012345678901234567890",
+        ] {
+            assert_eq!(src, big(src).to_string());
+        }
+        assert_eq!("255", BigInt::from_str_radix("ff", 16).unwrap().to_string());
+        assert_eq!(big("0"), big("-0"));
+        assert_eq!(None, BigInt::from_str_radix("12a", 10));
+    }
+
+    #[test]
+    fn i64_round_trip() {
+        for value in [0, 1, -1, i64::MAX, i64::MIN, 1 << 32] {
+            assert_eq!(Some(value), BigInt::from(value).to_i64());
+        }
+        assert_eq!(None, big("9223372036854775808").to_i64());
+        assert_eq!(None, big("-9223372036854775809").to_i64());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = big("123456789012345678901234567890");
+        let b = big("-987654321098765432");
+
+        assert_eq!(big("123456789011358024580135802458"), a.add(&b));
+        assert_eq!(big("123456789013333333222333333322"), a.sub(&b));
+        assert_eq!(
+            big("-121932631137021795212620027521140070120989178480"),
+            a.mul(&b)
+        );
+
+        let (quotient, remainder) = a.div_rem(&b).unwrap();
+        assert_eq!(big("-124999998860"), quotient);
+        assert_eq!(big("925925953827160370"), remainder);
+        assert_eq!(a, quotient.mul(&b).add(&remainder));
+
+        let (quotient, remainder) = b.div_rem(&big("7")).unwrap();
+        assert_eq!(big("-141093474442680776"), quotient);
+        assert_eq!(big("0"), remainder);
+
+        assert_eq!(None, a.div_rem(&BigInt::zero()));
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(big("-5") < big("3"));
+        assert!(big("-50000000000000") < big("-5"));
+        assert!(big("18446744073709551616") > big("18446744073709551615"));
+    }
+}
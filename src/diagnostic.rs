@@ -0,0 +1,318 @@
+use std::fmt::Write;
+
+use crate::{
+    error::{EnvError, EvalError, InterpreterError, LexerError, ParseError, SpannedError},
+    span::Span,
+};
+
+/// Describes how an error is presented to the user.
+pub trait Diagnose {
+    /// Stable identifier of the error, e.g. `E0101`.
+    fn code(&self) -> &'static str;
+
+    /// Suggestion on how to resolve the error.
+    fn help(&self) -> Option<String> {
+        None
+    }
+}
+
+/// A renderable report of an error raised while running a program.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Option<Span>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        let (error, span) = match error.downcast_ref::<SpannedError>() {
+            Some(spanned) => (&spanned.error, Some(spanned.span)),
+            None => (error, None),
+        };
+
+        let (code, help) = if let Some(e) = error.downcast_ref::<LexerError>() {
+            (e.code(), e.help())
+        } else if let Some(e) = error.downcast_ref::<ParseError>() {
+            (e.code(), e.help())
+        } else if let Some(e) = error.downcast_ref::<EvalError>() {
+            (e.code(), e.help())
+        } else if let Some(e) = error.downcast_ref::<EnvError>() {
+            (e.code(), e.help())
+        } else if let Some(e) = error.downcast_ref::<InterpreterError>() {
+            (e.code(), e.help())
+        } else {
+            ("E0000", None)
+        };
+
+        Self {
+            code,
+            message: error.to_string(),
+            span,
+            help,
+        }
+    }
+
+    /// Renders the diagnostic, quoting and underlining the offending part of `source`.
+    ///
+    /// ```text
+    /// error[E0101]: Expected RightParen but got Semicolon
+    ///  --> <repl>:1:17
+    ///   |
+    /// 1 | let foo = (1 + 2;
+    ///   |                 ^
+    ///   = help: No right paren inside expression
+    /// ```
+    pub fn render(&self, source: &str, origin: &str) -> String {
+        let mut out = String::new();
+        writeln!(out, "error[{}]: {}", self.code, self.message).unwrap();
+
+        let gutter = self.span.map_or(0, |span| span.line.to_string().len());
+        let pad = " ".repeat(gutter);
+
+        if let Some(span) = self.span {
+            writeln!(out, "{pad}--> {origin}:{span}").unwrap();
+
+            if let Some(line) = source.lines().nth(span.line - 1) {
+                let line_start = line.as_ptr() as usize - source.as_ptr() as usize;
+                let line_end = line_start + line.len();
+
+                let width = source
+                    .get(span.start.min(line_end)..span.end.min(line_end))
+                    .map_or(0, |s| s.chars().count())
+                    .max(1);
+
+                writeln!(out, "{pad} |").unwrap();
+                writeln!(out, "{} | {line}", span.line).unwrap();
+                writeln!(
+                    out,
+                    "{pad} | {}{}",
+                    " ".repeat(span.column - 1),
+                    "^".repeat(width)
+                )
+                .unwrap();
+            }
+        }
+
+        if let Some(help) = &self.help {
+            writeln!(out, "{pad} = help: {help}").unwrap();
+        }
+
+        out
+    }
+}
+
+impl Diagnose for LexerError {
+    fn code(&self) -> &'static str {
+        match self {
+            LexerError::UnexpectedCharacter(_) => "E0001",
+            LexerError::UnterminatedString => "E0002",
+            LexerError::InvalidEscape(_) => "E0003",
+            LexerError::InvalidUnicodeEscape(_) => "E0004",
+            LexerError::MalformedNumber(..) => "E0005",
+        }
+    }
+
+    fn help(&self) -> Option<String> {
+        match self {
+            LexerError::UnterminatedString => Some("add a closing `\"`".to_owned()),
+            LexerError::InvalidEscape(_) => {
+                Some("supported escapes are \\n, \\t, \\\", \\\\, \\{, \\} and \\u{...}".to_owned())
+            }
+            LexerError::InvalidUnicodeEscape(_) => Some(
+                "unicode escapes take 1 to 6 hex digits of a valid code point, e.g. \\u{1F600}"
+                    .to_owned(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl Diagnose for ParseError {
+    fn code(&self) -> &'static str {
+        match self {
+            ParseError::ExpectedCharacter(..) => "E0101",
+            ParseError::ExpectedToken => "E0102",
+            ParseError::ExpectedParameterToBeString(_) => "E0103",
+            ParseError::UnsupportedTokenType(_) => "E0104",
+            ParseError::NoDotOperatorWithoutRhsIdentifier => "E0105",
+            ParseError::ConstValueRequired => "E0106",
+            ParseError::InvalidNumericLiteral(_) => "E0107",
+            ParseError::RequiredParameterAfterDefault(_) => "E0108",
+            ParseError::RestParameterNotLast(_) => "E0109",
+            ParseError::PositionalAfterNamedArgument => "E0110",
+            ParseError::ReturnOutsideFunction => "E0111",
+            ParseError::MisplacedReference(_) => "E0112",
+        }
+    }
+
+    fn help(&self) -> Option<String> {
+        match self {
+            ParseError::ExpectedCharacter(_, _, hint) => Some(hint.to_owned()),
+            ParseError::ExpectedParameterToBeString(_) => {
+                Some("parameters are plain names, e.g. `fn add(x, y) { ... }`".to_owned())
+            }
+            ParseError::RequiredParameterAfterDefault(name) => Some(format!(
+                "give `{name}` a default value or move it before the defaulted parameters"
+            )),
+            ParseError::RestParameterNotLast(name) => Some(format!(
+                "`...{name}` collects the remaining arguments, so it must end the parameter list and cannot have a default"
+            )),
+            ParseError::PositionalAfterNamedArgument => {
+                Some("pass the remaining arguments by name or move them first".to_owned())
+            }
+            ParseError::NoDotOperatorWithoutRhsIdentifier => {
+                Some("use `object[key]` to access a computed property".to_owned())
+            }
+            ParseError::ConstValueRequired => {
+                Some("give the constant a value, e.g. `const foo = 0;`".to_owned())
+            }
+            ParseError::MisplacedReference(name) => Some(format!(
+                "a reference is passed on its own, `f(@{name})`, bound with `let r = @{name};` or assigned through, `@{name} += 1;`"
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl Diagnose for EvalError {
+    fn code(&self) -> &'static str {
+        match self {
+            EvalError::InvalidAssignment => "E0201",
+            EvalError::InvalidOperator(_) => "E0202",
+            EvalError::ValueNotAFunction(_) => "E0203",
+            EvalError::InvalidOperands(..) => "E0204",
+            EvalError::DivisionByZero => "E0205",
+            EvalError::IntegerOverflow { .. } => "E0206",
+            EvalError::InvalidOperand(..) => "E0207",
+            EvalError::NegationOverflow(_) => "E0208",
+            EvalError::InvalidRangeBound(_) => "E0209",
+            EvalError::NotIterable(_) => "E0210",
+            EvalError::ArityMismatch { .. } => "E0211",
+            EvalError::UnknownArgument { .. } => "E0212",
+            EvalError::DuplicateArgument(_) => "E0213",
+            EvalError::MissingArgument { .. } => "E0214",
+            EvalError::MissingProperty(_) => "E0215",
+            EvalError::InvalidMemberAccess(..) => "E0216",
+            EvalError::IndexOutOfBounds { .. } => "E0217",
+            EvalError::RangeBoundOutOfRange(_) => "E0218",
+            EvalError::CallDepthExceeded(..) => "E0219",
+        }
+    }
+
+    fn help(&self) -> Option<String> {
+        match self {
+            EvalError::InvalidAssignment => {
+                Some(
+                    "only variables and their properties can be assigned to, e.g. `foo.bar = 5;`"
+                        .to_owned(),
+                )
+            }
+            EvalError::InvalidMemberAccess(..) => Some(
+                "objects are accessed by name, `obj.key` or `obj[\"key\"]`, and arrays by integer index, `xs[0]`"
+                    .to_owned(),
+            ),
+            EvalError::CallDepthExceeded(name, _) => Some(format!(
+                "make sure every path through {name} eventually returns without calling it again"
+            )),
+            EvalError::IntegerOverflow { .. } | EvalError::NegationOverflow(_) => Some(
+                "run without --checked, --wrapping or --saturating to promote to arbitrary precision"
+                    .to_owned(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl Diagnose for EnvError {
+    fn code(&self) -> &'static str {
+        match self {
+            EnvError::RedeclareVariable(_) => "E0301",
+            EnvError::ReassignVariable(_) => "E0302",
+            EnvError::VariableNotFound(_) => "E0303",
+            EnvError::ReferenceToConstant(_) => "E0304",
+            EnvError::AssignReference(_) => "E0305",
+        }
+    }
+
+    fn help(&self) -> Option<String> {
+        match self {
+            EnvError::RedeclareVariable(name) => Some(format!(
+                "assign to the existing variable instead, e.g. `{name} = ...;`"
+            )),
+            EnvError::ReassignVariable(name) => Some(format!(
+                "declare it with `let {name}` if it needs to change"
+            )),
+            EnvError::VariableNotFound(name) => {
+                Some(format!("declare it first, e.g. `let {name} = ...;`"))
+            }
+            EnvError::ReferenceToConstant(name) => Some(format!(
+                "references can modify their target, declare it with `let {name}` instead"
+            )),
+            EnvError::AssignReference(_) => Some(
+                "bind references to new variables or parameters, e.g. `let r = @x;`".to_owned(),
+            ),
+        }
+    }
+}
+
+impl Diagnose for InterpreterError {
+    fn code(&self) -> &'static str {
+        match self {
+            InterpreterError::UnexpectedStatement(_) => "E0401",
+            InterpreterError::BreakOutsideLoop => "E0402",
+            InterpreterError::ContinueOutsideLoop => "E0403",
+            InterpreterError::BreakWithValue(_) => "E0404",
+            InterpreterError::ReturnOutsideFunction => "E0405",
+        }
+    }
+
+    fn help(&self) -> Option<String> {
+        match self {
+            InterpreterError::BreakWithValue(_) => {
+                Some("use `loop` to break out with a value".to_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn render_parse_error() {
+        let input = "let foo = 1;\nlet bar = (foo + 2;";
+
+        let error = Parser::new()
+            .produce_ast(input.to_string())
+            .expect_err("Should not parse");
+
+        let expected = "\
+error[E0101]: Expected RightParen but got Semicolon
+ --> test:2:19
+  |
+2 | let bar = (foo + 2;
+  |                   ^
+  = help: No right paren inside expression
+";
+        assert_eq!(
+            expected,
+            Diagnostic::from_error(&error).render(input, "test")
+        );
+    }
+
+    #[test]
+    fn render_without_span() {
+        let error = anyhow::anyhow!(EnvError::VariableNotFound("foo".to_owned()));
+
+        let expected = "\
+error[E0303]: Cannot resolve foo since it doesnt exist
+ = help: declare it first, e.g. `let foo = ...;`
+";
+        assert_eq!(expected, Diagnostic::from_error(&error).render("", "test"));
+    }
+}
@@ -1,34 +1,72 @@
 use anyhow::anyhow;
 use std::{
+    cell::RefCell,
     collections::{HashMap, HashSet},
+    rc::{Rc, Weak},
 };
 
 use crate::{
     error::{EnvError, Result},
+    interpreter::ArithmeticMode,
     values::{self, RuntimeValue},
 };
 
-#[derive(Debug, PartialEq, Clone)]
+/// A handle to a scope of variables and the chain of scopes it was created in.
+///
+/// Cloning a handle shares the scopes, so a function captures its environment
+/// by reference: it sees variables declared later on in the same scope,
+/// including itself, and every later assignment to them.
+///
+/// Scopes do not point at their parent, the chain is kept by each handle. A
+/// value stored in a scope that its own chain leads back to would keep that
+/// scope alive forever, for example a function declared in a nested block and
+/// assigned to a variable outside of it. While such a value is stored, its
+/// handle to the storing scope is weak. Reading the binding clones the value,
+/// which turns the handle back into a strong one.
 pub struct Environment {
-    parent: Option<Box<Environment>>,
+    /// This scope first, the global scope last.
+    chain: Vec<Handle>,
+}
+
+enum Handle {
+    Strong(Rc<RefCell<Scope>>),
+    Weak(Weak<RefCell<Scope>>),
+}
+
+struct Scope {
     variables: HashMap<String, RuntimeValue>,
     constants: HashSet<String>,
+    arithmetic: ArithmeticMode,
+}
+
+impl Handle {
+    fn scope(&self) -> Rc<RefCell<Scope>> {
+        match self {
+            Handle::Strong(scope) => scope.clone(),
+            Handle::Weak(scope) => scope
+                .upgrade()
+                .expect("Scope was dropped while a value bound in it was used"),
+        }
+    }
+
+    fn as_ptr(&self) -> *const RefCell<Scope> {
+        match self {
+            Handle::Strong(scope) => Rc::as_ptr(scope),
+            Handle::Weak(scope) => scope.as_ptr(),
+        }
+    }
 }
 
 impl Environment {
     pub fn new() -> Self {
-        let mut global = Self {
-            parent: None,
-            variables: HashMap::new(),
-            constants: HashSet::new(),
-        };
+        let global = Self::from_scope(None, ArithmeticMode::default());
 
         global.setup_scope();
 
         global
     }
 
-    fn setup_scope(&mut self) {
+    fn setup_scope(&self) {
         self.declare_var("true", RuntimeValue::Boolean(true), true);
         self.declare_var("false", RuntimeValue::Boolean(false), true);
         self.declare_var("null", RuntimeValue::Null, true);
@@ -41,82 +79,243 @@ impl Environment {
 
             RuntimeValue::Null
         }
-        self.declare_var("print", RuntimeValue::NativeFn(print), true);
+        let print = RuntimeValue::NativeFn {
+            name: "print",
+            call: print,
+        };
+        self.declare_var("print", print, true);
         fn time(
             _args: Vec<values::RuntimeValue>,
             _environment: &mut Environment,
         ) -> values::RuntimeValue {
-            RuntimeValue::Number("Friday 13th".to_string())
+            RuntimeValue::String("Friday 13th".to_string())
         }
-        self.declare_var("time", RuntimeValue::NativeFn(time), true);
+        let time = RuntimeValue::NativeFn {
+            name: "time",
+            call: time,
+        };
+        self.declare_var("time", time, true);
     }
 
-    pub fn with(parent_env: Environment) -> Self {
-        Self {
-            parent: Some(Box::new(parent_env)),
+    /// Creates a child scope of `parent_env`.
+    pub fn with(parent_env: &Environment) -> Self {
+        Self::from_scope(Some(parent_env), parent_env.arithmetic_mode())
+    }
+
+    fn from_scope(parent: Option<&Environment>, arithmetic: ArithmeticMode) -> Self {
+        let scope = Handle::Strong(Rc::new(RefCell::new(Scope {
             variables: HashMap::new(),
             constants: HashSet::new(),
+            arithmetic,
+        })));
+
+        let mut chain = vec![scope];
+        if let Some(parent) = parent {
+            chain.extend(parent.clone().chain);
         }
+
+        Self { chain }
+    }
+
+    fn scope(&self) -> Rc<RefCell<Scope>> {
+        self.chain[0].scope()
+    }
+
+    /// The environment of the scope at `depth` in the chain.
+    fn ancestor(&self, depth: usize) -> Environment {
+        Self {
+            chain: self.chain[depth..]
+                .iter()
+                .map(|handle| Handle::Strong(handle.scope()))
+                .collect(),
+        }
+    }
+
+    /// How integer overflow is handled by code running in this environment,
+    /// inherited by every scope created from it.
+    pub fn arithmetic_mode(&self) -> ArithmeticMode {
+        self.scope().borrow().arithmetic
+    }
+
+    pub fn set_arithmetic_mode(&self, mode: ArithmeticMode) {
+        self.scope().borrow_mut().arithmetic = mode;
     }
 
     pub fn declare_var(
-        &mut self,
+        &self,
         name: &str,
         value: RuntimeValue,
         constant: bool,
     ) -> Result<RuntimeValue> {
-        if self.variables.contains_key(name) {
+        let handle = self.scope();
+        let mut scope = handle.borrow_mut();
+        if scope.variables.contains_key(name) {
             return Err(anyhow!(EnvError::RedeclareVariable(name.to_string())));
         }
 
         if constant {
-            self.constants.insert(name.to_owned());
+            scope.constants.insert(name.to_owned());
         }
 
-        self.variables.insert(name.to_string(), value.clone());
+        let mut variable = value.clone();
+        detach(&handle, &mut variable);
+        scope.variables.insert(name.to_string(), variable);
         Ok(value)
     }
 
-    pub fn assign_var(&mut self, name: &str, value: RuntimeValue) -> Result<RuntimeValue> {
-        let env = self.resolve(name)?;
+    /// Variables bound to a reference assign to its target. A reference can
+    /// only be bound by a declaration, so references never point at another
+    /// reference.
+    pub fn assign_var(&self, name: &str, value: RuntimeValue) -> Result<RuntimeValue> {
+        if let RuntimeValue::Reference { .. } = value {
+            return Err(anyhow!(EnvError::AssignReference(name.to_string())));
+        }
 
-        if env.constants.contains(name) {
+        self.modify_var(name, |variable| {
+            *variable = value.clone();
+            Ok(value)
+        })
+    }
+
+    /// Runs `modify` on the value of `name` in place, in the scope that
+    /// declares it or through the reference it is bound to. Constants cannot
+    /// be modified, not even partially.
+    pub fn modify_var<T>(
+        &self,
+        name: &str,
+        modify: impl FnOnce(&mut RuntimeValue) -> Result<T>,
+    ) -> Result<T> {
+        let handle = self.chain[self.resolve(name)?].scope();
+        let mut scope = handle.borrow_mut();
+
+        if scope.constants.contains(name) {
             return Err(anyhow!(EnvError::ReassignVariable(name.to_string())));
         }
 
-        env.variables.insert(name.to_owned(), value.clone());
-        Ok(value)
+        if let Some(RuntimeValue::Reference {
+            name,
+            scope: target,
+        }) = scope.variables.get(name)
+        {
+            let (name, target) = (name.clone(), target.clone());
+            drop(scope);
+            return target.modify_var(&name, modify);
+        }
+
+        let variable = scope
+            .variables
+            .get_mut(name)
+            .expect("Variable was resolved but doesnt exist");
+        let result = modify(variable);
+        detach(&handle, variable);
+        result
     }
 
-    pub fn lookup_var(&mut self, name: &str) -> Result<RuntimeValue> {
-        let env = self.resolve(name)?;
-        let value = env
+    /// The value of `name`, or of the target of the reference it is bound to.
+    pub fn lookup_var(&self, name: &str) -> Result<RuntimeValue> {
+        let value = self.chain[self.resolve(name)?]
+            .scope()
+            .borrow()
             .variables
             .get(name)
-            .expect("Variable was resolved but doesnt exist");
-        Ok(value.clone())
+            .expect("Variable was resolved but doesnt exist")
+            .clone();
+
+        value.dereference()
     }
 
-    pub fn resolve(&mut self, name: &str) -> Result<&mut Environment> {
-        if self.variables.contains_key(name) {
-            return Ok(self);
+    /// `@name`, a reference to the binding of `name`. Referencing a variable
+    /// that is bound to a reference shares its target.
+    pub fn reference(&self, name: &str) -> Result<RuntimeValue> {
+        let depth = self.resolve(name)?;
+        let scope = self.chain[depth].scope();
+        let scope = scope.borrow();
+
+        if scope.constants.contains(name) {
+            return Err(anyhow!(EnvError::ReferenceToConstant(name.to_string())));
+        }
+
+        match scope.variables.get(name) {
+            Some(reference @ RuntimeValue::Reference { .. }) => Ok(reference.clone()),
+            _ => Ok(RuntimeValue::Reference {
+                name: name.to_owned(),
+                scope: self.ancestor(depth),
+            }),
         }
+    }
+
+    /// The depth in the chain of the scope that declares `name`.
+    fn resolve(&self, name: &str) -> Result<usize> {
+        self.chain
+            .iter()
+            .position(|handle| handle.scope().borrow().variables.contains_key(name))
+            .ok_or_else(|| anyhow!(EnvError::VariableNotFound(name.to_string())))
+    }
+}
 
-        if self.parent.is_none() {
-            return Err(anyhow!(EnvError::VariableNotFound(name.to_string())));
+/// Weakens the handles in `value` that lead back to `scope`, before it is
+/// stored there.
+fn detach(scope: &Rc<RefCell<Scope>>, value: &mut RuntimeValue) {
+    match value {
+        RuntimeValue::Function {
+            declaration_env: env,
+            ..
+        }
+        | RuntimeValue::Reference { scope: env, .. } => {
+            for handle in &mut env.chain {
+                if std::ptr::eq(handle.as_ptr(), Rc::as_ptr(scope)) {
+                    *handle = Handle::Weak(Rc::downgrade(scope));
+                }
+            }
         }
+        RuntimeValue::Array(elements) => elements
+            .iter_mut()
+            .for_each(|element| detach(scope, element)),
+        RuntimeValue::Object(properties) => properties
+            .values_mut()
+            .for_each(|value| detach(scope, value)),
+        _ => {}
+    }
+}
 
-        self.parent.as_mut().unwrap().resolve(name)
+/// Clones are always strong, so values read from a scope keep it alive.
+impl Clone for Environment {
+    fn clone(&self) -> Self {
+        self.ancestor(0)
+    }
+}
+
+/// Only lists variable names, values may refer back to this environment.
+impl std::fmt::Debug for Environment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scope = self.scope();
+        let scope = scope.borrow();
+        let mut names = scope.variables.keys().collect::<Vec<_>>();
+        names.sort();
+
+        let parent = (self.chain.len() > 1).then(|| self.ancestor(1));
+        f.debug_struct("Environment")
+            .field("variables", &names)
+            .field("parent", &parent)
+            .finish()
+    }
+}
+
+/// Handles are equal when they refer to the same scope.
+impl PartialEq for Environment {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.chain[0].as_ptr(), other.chain[0].as_ptr())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{ast::StmtKind, interpreter::evaluate, parser::Parser};
 
     #[test]
     fn basic() {
-        let mut env = Environment::new();
+        let env = Environment::new();
         assert_eq!(RuntimeValue::Boolean(true), env.lookup_var("true").unwrap());
         assert_eq!(
             RuntimeValue::Boolean(false),
@@ -130,4 +329,127 @@ mod tests {
                 .to_string(),
         );
     }
+
+    #[test]
+    fn shared_scopes() {
+        let global = Environment::new();
+        let child = Environment::with(&global);
+        let handle = child.clone();
+
+        global
+            .declare_var("later", RuntimeValue::Integer(1), false)
+            .unwrap();
+        handle
+            .declare_var("local", RuntimeValue::Integer(2), true)
+            .unwrap();
+
+        assert_eq!(RuntimeValue::Integer(1), child.lookup_var("later").unwrap());
+        assert_eq!(RuntimeValue::Integer(2), child.lookup_var("local").unwrap());
+        assert!(global.lookup_var("local").is_err());
+
+        child.assign_var("later", RuntimeValue::Integer(3)).unwrap();
+        assert_eq!(
+            RuntimeValue::Integer(3),
+            global.lookup_var("later").unwrap()
+        );
+        assert!(handle.assign_var("local", RuntimeValue::Null).is_err());
+
+        assert_eq!(child, handle);
+        assert_ne!(child, Environment::with(&global));
+    }
+
+    #[test]
+    fn functions_do_not_keep_their_own_scope_alive() {
+        let mut env = Environment::new();
+        let mut run = |src: &str| {
+            let program = Parser::new().produce_ast(src.to_owned()).unwrap();
+            evaluate(StmtKind::Program(program).into(), &mut env).unwrap()
+        };
+        run("fn mk() { let big = [1, 2]; fn inner() { big } inner }; let f = mk();");
+
+        let RuntimeValue::Function {
+            declaration_env: call,
+            ..
+        } = run("f")
+        else {
+            panic!("Expected a function");
+        };
+        let scope = Rc::downgrade(&call.scope());
+        drop(call);
+
+        // Only `f` holds on to the scope of the call, `inner` inside it is weak
+        assert_eq!(1, scope.strong_count());
+        assert_eq!("[1, 2]", run("f()").to_string());
+
+        run("f = null");
+        assert_eq!(0, scope.strong_count());
+    }
+
+    #[test]
+    fn functions_stored_outside_their_block_do_not_keep_it_alive() {
+        let mut env = Environment::new();
+        let mut run = |src: &str| {
+            let program = Parser::new().produce_ast(src.to_owned()).unwrap();
+            evaluate(StmtKind::Program(program).into(), &mut env).unwrap()
+        };
+        run("
+            fn mk() {
+                let o = { n: 2 };
+                if true { let k = 3; o.f = fn() { o.n * k } };
+                o
+            }
+            let o = mk();
+        ");
+
+        let RuntimeValue::Function {
+            declaration_env: block,
+            body,
+            ..
+        } = run("o.f")
+        else {
+            panic!("Expected a function");
+        };
+        let RuntimeValue::Function { body: shared, .. } = run("o.f") else {
+            panic!("Expected a function");
+        };
+        assert!(Rc::ptr_eq(&body, &shared));
+
+        let call = Rc::downgrade(&block.ancestor(1).scope());
+        let scope = Rc::downgrade(&block.scope());
+        drop(block);
+        assert_eq!("6", run("o.f()").to_string());
+
+        // The function in the call scope only weakly refers back to it
+        run("o = null");
+        assert_eq!(0, scope.strong_count());
+        assert_eq!(0, call.strong_count());
+    }
+
+    #[test]
+    fn references() {
+        let global = Environment::new();
+        global
+            .declare_var("x", RuntimeValue::Integer(1), false)
+            .unwrap();
+
+        let child = Environment::with(&global);
+        let reference = child.reference("x").unwrap();
+        child.declare_var("r", reference.clone(), false).unwrap();
+
+        assert_eq!(RuntimeValue::Integer(1), child.lookup_var("r").unwrap());
+        child.assign_var("r", RuntimeValue::Integer(2)).unwrap();
+        assert_eq!(RuntimeValue::Integer(2), global.lookup_var("x").unwrap());
+
+        // References to a reference share its target
+        assert_eq!(reference, child.reference("r").unwrap());
+
+        assert_eq!(
+            "Cannot assign a reference to existing variable r",
+            child.assign_var("r", reference).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Cannot take a reference to constant null",
+            child.reference("null").unwrap_err().to_string()
+        );
+    }
 }
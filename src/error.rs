@@ -1,9 +1,39 @@
 use thiserror::Error;
 
-use crate::{ast::Stmt, token::TokenType};
+use anyhow::anyhow;
+
+use crate::{span::Span, token::TokenType};
 
 pub type Result<T> = anyhow::Result<T>;
 
+/// An error tied to the region of source code it was raised for.
+#[derive(Error, Debug)]
+#[error("{error}")]
+pub struct SpannedError {
+    pub error: anyhow::Error,
+    pub span: Span,
+}
+
+pub trait WithSpan<T> {
+    /// Attaches `span` to the error, unless it already points at a more specific location.
+    fn with_span(self, span: Span) -> Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> WithSpan<T> for std::result::Result<T, E> {
+    fn with_span(self, span: Span) -> Result<T> {
+        self.map_err(|e| with_span(e.into(), span))
+    }
+}
+
+/// Attaches `span` to `error`, see `WithSpan::with_span`.
+pub fn with_span(error: anyhow::Error, span: Span) -> anyhow::Error {
+    if error.is::<SpannedError>() {
+        error
+    } else {
+        anyhow!(SpannedError { error, span })
+    }
+}
+
 #[derive(Error, Debug, PartialEq)]
 pub enum EnvError {
     #[error("Cannot redeclare variable {0}")]
@@ -12,42 +42,126 @@ pub enum EnvError {
     ReassignVariable(String),
     #[error("Cannot resolve {0} since it doesnt exist")]
     VariableNotFound(String),
+    #[error("Cannot take a reference to constant {0}")]
+    ReferenceToConstant(String),
+    #[error("Cannot assign a reference to existing variable {0}")]
+    AssignReference(String),
 }
 
 #[derive(Error, Debug, PartialEq)]
 pub enum EvalError {
     #[error("Invalid assignment")]
     InvalidAssignment,
-    #[error("Unsupported binary operator {0}")]
+    #[error("Unsupported operator {0}")]
     InvalidOperator(String),
-    #[error("Value {0:?} is not a function")]
-    ValueNotAFunction(Stmt),
+    #[error("Value {0} is not a function")]
+    ValueNotAFunction(String),
+    #[error("Cannot apply operator {0} to {1} and {2}")]
+    InvalidOperands(String, &'static str, &'static str),
+    #[error("Cannot apply operator {0} to {1}")]
+    InvalidOperand(String, &'static str),
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("Integer overflow in {lhs} {operator} {rhs}")]
+    IntegerOverflow {
+        lhs: i64,
+        operator: String,
+        rhs: i64,
+    },
+    #[error("Integer overflow in -({0})")]
+    NegationOverflow(i64),
+    #[error("Range bounds must be integers, got {0}")]
+    InvalidRangeBound(&'static str),
+    #[error("Range bound {0} does not fit in a 64-bit integer")]
+    RangeBoundOutOfRange(String),
+    #[error("Cannot iterate over {0}")]
+    NotIterable(&'static str),
+    #[error("{name} expects {} but got {actual}", arguments(*.min, *.max))]
+    ArityMismatch {
+        name: String,
+        min: usize,
+        max: Option<usize>,
+        actual: usize,
+    },
+    #[error("{name} has no parameter named {argument}")]
+    UnknownArgument { name: String, argument: String },
+    #[error("Argument {0} is given more than once")]
+    DuplicateArgument(String),
+    #[error("{name} is missing an argument for {parameter}")]
+    MissingArgument { name: String, parameter: String },
+    #[error("Object has no property {0}")]
+    MissingProperty(String),
+    #[error("Cannot access property {0} of {1}")]
+    InvalidMemberAccess(String, &'static str),
+    #[error("Index {index} is out of bounds for an array of length {length}")]
+    IndexOutOfBounds { index: i64, length: usize },
+    #[error("Calling {0} exceeds the maximum call depth of {1}")]
+    CallDepthExceeded(String, usize),
+}
+
+/// "1 argument", "2 arguments", "1 to 3 arguments", "at least 1 argument"
+fn arguments(min: usize, max: Option<usize>) -> String {
+    match (min, max) {
+        (1, Some(1)) => "1 argument".to_owned(),
+        (min, Some(max)) if min == max => format!("{min} arguments"),
+        (min, Some(max)) => format!("{min} to {max} arguments"),
+        (1, None) => "at least 1 argument".to_owned(),
+        (min, None) => format!("at least {min} arguments"),
+    }
 }
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ParseError {
-    #[error("\nExpected {0:?} but got {1:?}.\n{2}")]
+    #[error("Expected {0:?} but got {1:?}")]
     ExpectedCharacter(TokenType, TokenType, String),
     #[error("Expected a token but it returned nothing")]
     ExpectedToken,
-    #[error("Expected parameter type {0:?} to be of type string")]
-    ExpectedParameterToBeString(Stmt),
+    #[error("Expected parameter to be an identifier but got a {0}")]
+    ExpectedParameterToBeString(&'static str),
     #[error("Unsupported token {0:?}")]
     UnsupportedTokenType(TokenType),
     #[error("Cannot use dot operator without rhs being an identifier")]
     NoDotOperatorWithoutRhsIdentifier,
     #[error("A value is required for const assignment")]
     ConstValueRequired,
+    #[error("Numeric literal {0} is out of range")]
+    InvalidNumericLiteral(String),
+    #[error("Parameter {0} without a default follows a parameter with one")]
+    RequiredParameterAfterDefault(String),
+    #[error("Rest parameter {0} must be the last parameter")]
+    RestParameterNotLast(String),
+    #[error("Positional argument follows a named argument")]
+    PositionalAfterNamedArgument,
+    #[error("`return` outside of a function")]
+    ReturnOutsideFunction,
+    #[error("Reference @{0} cannot be used here")]
+    MisplacedReference(String),
 }
 
 #[derive(Error, Debug, PartialEq)]
 pub enum LexerError {
     #[error("Unexpected character {0}")]
     UnexpectedCharacter(char),
+    #[error("Unterminated string literal")]
+    UnterminatedString,
+    #[error("Unknown escape sequence \\{0}")]
+    InvalidEscape(char),
+    #[error("Invalid unicode escape \\u{{{0}}}")]
+    InvalidUnicodeEscape(String),
+    #[error("Malformed numeric literal {0}, {1}")]
+    MalformedNumber(String, String),
 }
 
 #[derive(Error, Debug, PartialEq)]
 pub enum InterpreterError {
-    #[error("Unexpected character {0:?}")]
-    UnexpectedStatement(Stmt),
+    #[error("Unexpected {0}")]
+    UnexpectedStatement(&'static str),
+    #[error("`break` outside of a loop")]
+    BreakOutsideLoop,
+    #[error("`continue` outside of a loop")]
+    ContinueOutsideLoop,
+    #[error("`break` with a value inside a `{0}` loop")]
+    BreakWithValue(&'static str),
+    #[error("`return` outside of a function")]
+    ReturnOutsideFunction,
 }
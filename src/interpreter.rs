@@ -1,84 +1,333 @@
-use std::collections::HashMap;
+use std::{cell::Cell, collections::HashMap, rc::Rc};
 
 use anyhow::anyhow;
 
 use crate::{
-    ast::{Property, Stmt},
+    ast::{Argument, Parameter, Property, Stmt, StmtKind, TemplatePart},
+    bigint::BigInt,
     environment::Environment,
-    error::{EvalError, InterpreterError, Result},
+    error::{with_span, EvalError, InterpreterError, Result},
+    span::Span,
     values::RuntimeValue,
 };
 
+/// How integer arithmetic behaves when the result does not fit in an `i64`.
+///
+/// Operations on integers that are already arbitrary precision are always exact.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ArithmeticMode {
+    /// Results are promoted to arbitrary precision integers.
+    #[default]
+    Promoting,
+    /// Overflow is reported as an `EvalError::IntegerOverflow`.
+    Checked,
+    /// Results wrap around at the boundary of the type.
+    Wrapping,
+    /// Results are clamped to `i64::MIN` or `i64::MAX`.
+    Saturating,
+}
+
+/// Calls nested deeper than this are reported as an error instead of
+/// overflowing the stack.
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+/// Stack size of the thread the interpreter runs on, each call takes up to a
+/// few dozen kilobytes in a debug build.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+thread_local! {
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Why evaluation stopped before producing a value.
+///
+/// Control flow travels on the error side of `Flow` so that `?` unwinds it
+/// out of nested expressions, up to the construct that handles it.
+enum Interrupt {
+    Error(anyhow::Error),
+    /// `break` with its optional value, spanning the statement.
+    Break(Option<Box<RuntimeValue>>, Span),
+    Continue(Span),
+    /// `return` with its optional value, spanning the statement.
+    Return(Option<Box<RuntimeValue>>, Span),
+}
+
+type Flow = std::result::Result<RuntimeValue, Interrupt>;
+
+impl From<anyhow::Error> for Interrupt {
+    fn from(error: anyhow::Error) -> Self {
+        Interrupt::Error(error)
+    }
+}
+
+impl Interrupt {
+    fn with_span(self, span: Span) -> Self {
+        match self {
+            Interrupt::Error(error) => Interrupt::Error(with_span(error, span)),
+            interrupt => interrupt,
+        }
+    }
+
+    /// Reports control flow that escaped every construct able to handle it.
+    fn into_error(self) -> anyhow::Error {
+        match self {
+            Interrupt::Error(error) => error,
+            Interrupt::Break(_, span) => {
+                with_span(anyhow!(InterpreterError::BreakOutsideLoop), span)
+            }
+            Interrupt::Continue(span) => {
+                with_span(anyhow!(InterpreterError::ContinueOutsideLoop), span)
+            }
+            Interrupt::Return(_, span) => {
+                with_span(anyhow!(InterpreterError::ReturnOutsideFunction), span)
+            }
+        }
+    }
+}
+
 pub fn evaluate(stmt: Stmt, env: &mut Environment) -> Result<RuntimeValue> {
-    match stmt {
-        Stmt::NumericLiteral(v) => Ok(RuntimeValue::Number(v)),
-        Stmt::Identifier(v) => eval_identifier(v, env),
-        Stmt::ObjectLiteral(properties) => eval_object_expr(properties, env),
-        Stmt::CallExpr { args, caller } => eval_call_expr(args, *caller, env),
-        Stmt::AssignmentExpr { assignee, value } => eval_assignment_expr(*assignee, *value, env),
-        Stmt::FnDeclaration {
+    eval(&stmt, env).map_err(Interrupt::into_error)
+}
+
+fn eval(stmt: &Stmt, env: &mut Environment) -> Flow {
+    let span = stmt.span;
+    match &stmt.kind {
+        StmtKind::IntegerLiteral(v) => Ok(RuntimeValue::Integer(*v)),
+        StmtKind::BigIntLiteral(v) => Ok(RuntimeValue::BigInt(v.clone())),
+        StmtKind::FloatLiteral(v) => Ok(RuntimeValue::Float(*v)),
+        StmtKind::StringLiteral(v) => Ok(RuntimeValue::String(v.clone())),
+        StmtKind::TemplateLiteral(parts) => eval_template_literal(parts, env),
+        StmtKind::Identifier(v) => eval_identifier(v, env),
+        StmtKind::Reference(name) => Ok(env.reference(name)?),
+        StmtKind::ObjectLiteral(properties) => eval_object_expr(properties, env),
+        StmtKind::ArrayLiteral(elements) => eval_array_literal(elements, env),
+        StmtKind::RangeExpr {
+            start,
+            end,
+            inclusive,
+        } => eval_range_expr(start, end, *inclusive, env),
+        StmtKind::CallExpr { args, caller } => eval_call_expr(args, caller, env),
+        StmtKind::MemberExpr {
+            object,
+            property,
+            computed,
+        } => eval_member_expr(object, property, *computed, env),
+        StmtKind::AssignmentExpr { assignee, value } => eval_assignment_expr(assignee, value, env),
+        StmtKind::CompoundAssignment {
+            assignee,
+            value,
+            operator,
+        } => eval_compound_assignment(assignee, value, operator, env),
+        StmtKind::FnDeclaration {
             name,
             parameters,
             body,
             is_const,
-        } => eval_function_declaration(name, parameters, body, is_const, env),
-        Stmt::VarDeclaration {
+        } => eval_function_declaration(name, parameters, body, *is_const, env),
+        StmtKind::FnExpr { parameters, body } => Ok(RuntimeValue::Function {
+            name: "<anonymous>".to_owned(),
+            parameters: parameters.clone(),
+            declaration_env: env.clone(),
+            body: body.clone(),
+        }),
+        StmtKind::VarDeclaration {
             constant,
             identifier,
             value,
-        } => eval_variable_declaration(constant, identifier, value, env),
-        Stmt::BinaryExpr {
+        } => eval_variable_declaration(*constant, identifier, value.as_deref(), env),
+        StmtKind::BinaryExpr {
+            left,
+            right,
+            operator,
+        } => evaluate_binary_expr(left, right, operator, env),
+        StmtKind::LogicalExpr {
             left,
             right,
             operator,
-        } => evaluate_binary_expr(*left, *right, operator, env),
-        Stmt::Program(program) => eval_program(program, env),
-        _ => Err(anyhow!(InterpreterError::UnexpectedStatement(stmt))),
+        } => eval_logical_expr(left, right, operator, env),
+        StmtKind::UnaryExpr { operator, operand } => eval_unary_expr(operator, operand, env),
+        StmtKind::IfExpr {
+            condition,
+            then_branch,
+            else_branch,
+        } => eval_if_expr(condition, then_branch, else_branch.as_deref(), env),
+        StmtKind::WhileLoop { condition, body } => eval_while_loop(condition, body, env),
+        StmtKind::Loop(body) => eval_loop(body, env),
+        StmtKind::ForLoop {
+            variable,
+            iterable,
+            body,
+        } => eval_for_loop(variable, iterable, body, env),
+        StmtKind::Break(value) => {
+            let value = value.as_ref().map(|value| eval(value, env)).transpose()?;
+            Err(Interrupt::Break(value.map(Box::new), span))
+        }
+        StmtKind::Continue => Err(Interrupt::Continue(span)),
+        StmtKind::Return(value) => {
+            let value = value.as_ref().map(|value| eval(value, env)).transpose()?;
+            Err(Interrupt::Return(value.map(Box::new), span))
+        }
+        StmtKind::Block(body) => eval_block(body, env),
+        StmtKind::Program(program) => eval_program(program, env),
+        kind => Err(anyhow!(InterpreterError::UnexpectedStatement(kind.name())).into()),
     }
+    .map_err(|interrupt| interrupt.with_span(span))
 }
 
-fn eval_call_expr(args: Vec<Stmt>, caller: Stmt, env: &mut Environment) -> Result<RuntimeValue> {
-    let args = args
-        .iter()
-        .map(|arg| evaluate(arg.to_owned(), env).expect("Cannot evaludate argument {arg:?}"))
-        .collect::<Vec<_>>();
+fn eval_call_expr(args: &[Argument], caller: &Stmt, env: &mut Environment) -> Flow {
+    let mut values = vec![];
+    let mut named: Vec<(String, RuntimeValue, Span)> = vec![];
+    for arg in args {
+        match arg {
+            Argument::Positional(arg) => values.push(eval(arg, env)?),
+            Argument::Named(name, arg) => {
+                let span = arg.span;
+                if named.iter().any(|(other, ..)| other == name) {
+                    let error = anyhow!(EvalError::DuplicateArgument(name.clone()));
+                    return Err(with_span(error, span).into());
+                }
+                named.push((name.clone(), eval(arg, env)?, span));
+            }
+            Argument::Spread(arg) => {
+                let span = arg.span;
+                let iterable = eval(arg, env)?;
+                let type_name = iterable.type_name();
+                let Some(items) = iterable.iterate() else {
+                    let error = anyhow!(EvalError::NotIterable(type_name));
+                    return Err(with_span(error, span).into());
+                };
+                values.extend(items);
+            }
+        }
+    }
+    let args = values;
 
-    let call_expr = evaluate(caller.to_owned(), env)?;
+    let call_expr = eval(caller, env)?;
 
     match call_expr {
-        RuntimeValue::NativeFn(function) => {
-            let result = function(args, env);
+        RuntimeValue::NativeFn { name, call } => {
+            if let Some((argument, _, span)) = named.into_iter().next() {
+                let error = anyhow!(EvalError::UnknownArgument {
+                    name: name.to_owned(),
+                    argument,
+                });
+                return Err(with_span(error, span).into());
+            }
+
+            // Native functions only read their arguments
+            let args = args
+                .into_iter()
+                .map(RuntimeValue::dereference)
+                .collect::<Result<_>>()?;
+            let result = call(args, env);
             Ok(result)
         }
         RuntimeValue::Function {
-            name: _,
+            name,
             parameters,
             declaration_env,
             body,
         } => {
-            let mut scope = Environment::with(declaration_env);
+            let min = parameters
+                .iter()
+                .filter(|p| p.default.is_none() && !p.rest)
+                .count();
+            let max = match parameters.last() {
+                Some(Parameter { rest: true, .. }) => None,
+                _ => Some(parameters.len()),
+            };
+            let actual = args.len() + named.len();
+            if actual < min || max.is_some_and(|max| args.len() > max) {
+                return Err(anyhow!(EvalError::ArityMismatch {
+                    name,
+                    min,
+                    max,
+                    actual,
+                })
+                .into());
+            }
 
-            for (i, parameter) in parameters.iter().enumerate() {
-                scope.declare_var(parameter, args[i].clone(), false)?;
+            // A rest parameter can only be filled positionally
+            for (argument, _, span) in &named {
+                let error = match parameters
+                    .iter()
+                    .position(|p| p.name == *argument && !p.rest)
+                {
+                    Some(position) if position >= args.len() => continue,
+                    Some(_) => EvalError::DuplicateArgument(argument.clone()),
+                    None => EvalError::UnknownArgument {
+                        name,
+                        argument: argument.clone(),
+                    },
+                };
+                return Err(with_span(anyhow!(error), *span).into());
             }
 
-            let mut result = RuntimeValue::Null;
+            let mut scope = Environment::with(&declaration_env);
+
+            let function = name;
+            let mut args = args.into_iter();
+            for Parameter {
+                name,
+                default,
+                rest,
+            } in parameters.iter()
+            {
+                if *rest {
+                    // The elements of a rest array are values, not bindings
+                    let rest = args
+                        .by_ref()
+                        .map(RuntimeValue::dereference)
+                        .collect::<Result<_>>()?;
+                    let rest = RuntimeValue::Array(rest);
+                    scope.declare_var(name, rest, false)?;
+                    break;
+                }
+
+                let arg = args.next().or_else(|| {
+                    let position = named.iter().position(|(other, ..)| other == name)?;
+                    Some(named.swap_remove(position).1)
+                });
+                let value = match (arg, default) {
+                    (Some(arg), _) => arg,
+                    (None, Some(default)) => eval(default, &mut scope)?,
+                    (None, None) => {
+                        return Err(anyhow!(EvalError::MissingArgument {
+                            name: function,
+                            parameter: name.clone(),
+                        })
+                        .into());
+                    }
+                };
+                scope.declare_var(name, value, false)?;
+            }
 
-            for stmt in body {
-                result = evaluate(stmt, &mut scope)?;
+            let depth = CALL_DEPTH.get();
+            if depth == MAX_CALL_DEPTH {
+                let error = EvalError::CallDepthExceeded(function, MAX_CALL_DEPTH);
+                return Err(anyhow!(error).into());
             }
 
-            Ok(result)
+            CALL_DEPTH.set(depth + 1);
+            let result = eval_body(&body, &mut scope);
+            CALL_DEPTH.set(depth);
+
+            match result {
+                Ok(result) => Ok(result),
+                Err(Interrupt::Return(value, _)) => Ok(value.map_or(RuntimeValue::Null, |v| *v)),
+                // Loop control cannot reach past the function it appears in
+                Err(interrupt) => Err(interrupt.into_error().into()),
+            }
         }
-        _ => Err(anyhow!(EvalError::ValueNotAFunction(caller))),
+        value => Err(anyhow!(EvalError::ValueNotAFunction(value.to_string())).into()),
     }
 }
 
-fn eval_program(program: crate::ast::Program, env: &mut Environment) -> Result<RuntimeValue> {
+fn eval_program(program: &crate::ast::Program, env: &mut Environment) -> Flow {
     let mut last_evaluated = RuntimeValue::Null;
 
-    for statement in program.body {
-        match evaluate(statement, env) {
+    for statement in &program.body {
+        match eval(statement, env) {
             Ok(v) => last_evaluated = v,
             Err(e) => {
                 return Err(e);
@@ -89,119 +338,1597 @@ fn eval_program(program: crate::ast::Program, env: &mut Environment) -> Result<R
     Ok(last_evaluated)
 }
 
-fn eval_object_expr(
-    object_properties: Vec<Property>,
+/// Yields the value of the taken branch, or null when no branch is taken.
+fn eval_if_expr(
+    condition: &Stmt,
+    then_branch: &[Stmt],
+    else_branch: Option<&Stmt>,
     env: &mut Environment,
-) -> Result<RuntimeValue> {
+) -> Flow {
+    if eval(condition, env)?.is_truthy() {
+        eval_block(then_branch, env)
+    } else if let Some(else_branch) = else_branch {
+        eval(else_branch, env)
+    } else {
+        Ok(RuntimeValue::Null)
+    }
+}
+
+/// Always yields null, so a `break` inside it may not carry a value.
+fn eval_while_loop(condition: &Stmt, body: &[Stmt], env: &mut Environment) -> Flow {
+    while eval(condition, env)?.is_truthy() {
+        match eval_block(body, env) {
+            Ok(_) | Err(Interrupt::Continue(_)) => {}
+            Err(Interrupt::Break(None, _)) => break,
+            Err(Interrupt::Break(Some(_), span)) => {
+                let error = anyhow!(InterpreterError::BreakWithValue("while"));
+                return Err(with_span(error, span).into());
+            }
+            Err(interrupt) => return Err(interrupt),
+        }
+    }
+
+    Ok(RuntimeValue::Null)
+}
+
+/// Yields the value given to the `break` that ends it, or null.
+fn eval_loop(body: &[Stmt], env: &mut Environment) -> Flow {
+    loop {
+        match eval_block(body, env) {
+            Ok(_) | Err(Interrupt::Continue(_)) => {}
+            Err(Interrupt::Break(value, _)) => {
+                return Ok(value.map_or(RuntimeValue::Null, |value| *value));
+            }
+            Err(interrupt) => return Err(interrupt),
+        }
+    }
+}
+
+/// Binds each item to `variable` in a fresh scope per iteration, so closures
+/// created in the body capture the item of their own iteration.
+fn eval_for_loop(variable: &str, iterable: &Stmt, body: &[Stmt], env: &mut Environment) -> Flow {
+    let iterable = eval(iterable, env)?;
+    let type_name = iterable.type_name();
+    let Some(items) = iterable.iterate() else {
+        return Err(anyhow!(EvalError::NotIterable(type_name)).into());
+    };
+
+    for item in items {
+        let mut scope = Environment::with(env);
+        let result = match scope.declare_var(variable, item, false) {
+            Ok(_) => eval_body(body, &mut scope),
+            Err(error) => Err(error.into()),
+        };
+
+        match result {
+            Ok(_) | Err(Interrupt::Continue(_)) => {}
+            Err(Interrupt::Break(None, _)) => break,
+            Err(Interrupt::Break(Some(_), span)) => {
+                let error = anyhow!(InterpreterError::BreakWithValue("for"));
+                return Err(with_span(error, span).into());
+            }
+            Err(interrupt) => return Err(interrupt),
+        }
+    }
+
+    Ok(RuntimeValue::Null)
+}
+
+/// Runs `body` in a child scope of `env`, yielding the last value.
+fn eval_block(body: &[Stmt], env: &mut Environment) -> Flow {
+    eval_body(body, &mut Environment::with(env))
+}
+
+/// Runs each statement of `body` in `env`, yielding the last value.
+fn eval_body(body: &[Stmt], env: &mut Environment) -> Flow {
+    let mut result = RuntimeValue::Null;
+    for stmt in body {
+        result = eval(stmt, env)?;
+    }
+    Ok(result)
+}
+
+fn eval_array_literal(elements: &[Stmt], env: &mut Environment) -> Flow {
+    let mut values = vec![];
+    for element in elements {
+        values.push(eval(element, env)?);
+    }
+
+    Ok(RuntimeValue::Array(values))
+}
+
+fn eval_range_expr(start: &Stmt, end: &Stmt, inclusive: bool, env: &mut Environment) -> Flow {
+    let bound = |value: RuntimeValue| match value {
+        RuntimeValue::Integer(i) => Ok(i),
+        RuntimeValue::BigInt(i) => Err(anyhow!(EvalError::RangeBoundOutOfRange(i.to_string()))),
+        value => Err(anyhow!(EvalError::InvalidRangeBound(value.type_name()))),
+    };
+
+    let start = bound(eval(start, env)?)?;
+    let end = bound(eval(end, env)?)?;
+
+    Ok(RuntimeValue::Range {
+        start,
+        end,
+        inclusive,
+    })
+}
+
+fn eval_object_expr(object_properties: &[Property], env: &mut Environment) -> Flow {
     let mut properties = HashMap::new();
     for property in object_properties {
         let Property { key, value } = property;
 
         let value = if let Some(value) = value {
-            evaluate(*value, env)?
+            eval(value, env)?
         } else {
-            match env.lookup_var(&key) {
-                Ok(v) => v,
-                Err(e) => {
-                    return Err(anyhow!(e));
-                }
-            }
+            env.lookup_var(key)?
         };
 
-        properties.insert(key, value);
+        properties.insert(key.clone(), value);
     }
 
     Ok(RuntimeValue::Object(properties))
 }
 
-fn eval_assignment_expr(
-    assignee: Stmt,
-    value: Stmt,
+fn eval_member_expr(object: &Stmt, property: &Stmt, computed: bool, env: &mut Environment) -> Flow {
+    let object = eval(object, env)?;
+    let key = eval_property_key(property, computed, env)?;
+
+    Ok(get_property(object, key)?)
+}
+
+/// The name of `obj.name`, or the evaluated key of `obj[key]`.
+fn eval_property_key(property: &Stmt, computed: bool, env: &mut Environment) -> Flow {
+    match &property.kind {
+        StmtKind::Identifier(name) if !computed => Ok(RuntimeValue::String(name.clone())),
+        _ => eval(property, env),
+    }
+}
+
+/// Reads a property of an object or an element of an array.
+fn get_property(object: RuntimeValue, key: RuntimeValue) -> Result<RuntimeValue> {
+    match (object, key) {
+        (RuntimeValue::Object(mut properties), RuntimeValue::String(key)) => properties
+            .remove(&key)
+            .ok_or_else(|| anyhow!(EvalError::MissingProperty(key))),
+        (RuntimeValue::Array(mut elements), RuntimeValue::Integer(index)) => {
+            let position = array_index(index, elements.len())?;
+            Ok(elements.swap_remove(position))
+        }
+        (object, key) => Err(anyhow!(EvalError::InvalidMemberAccess(
+            key.to_string(),
+            object.type_name()
+        ))),
+    }
+}
+
+fn array_index(index: i64, length: usize) -> Result<usize> {
+    usize::try_from(index)
+        .ok()
+        .filter(|position| *position < length)
+        .ok_or_else(|| anyhow!(EvalError::IndexOutOfBounds { index, length }))
+}
+
+fn eval_template_literal(parts: &[TemplatePart], env: &mut Environment) -> Flow {
+    let mut string = String::new();
+
+    for part in parts {
+        match part {
+            TemplatePart::Literal(literal) => string.push_str(literal),
+            TemplatePart::Expr(expr) => string.push_str(&eval(expr, env)?.to_string()),
+        }
+    }
+
+    Ok(RuntimeValue::String(string))
+}
+
+/// `a = v`, `a.b[c] = v`, the keys are evaluated before the value.
+fn eval_assignment_expr(assignee: &Stmt, value: &Stmt, env: &mut Environment) -> Flow {
+    let mut path = vec![];
+    let name = eval_assignment_target(assignee, &mut path, env)?;
+    let value = eval(value, env)?;
+
+    if path.is_empty() {
+        return Ok(env.assign_var(name, value)?);
+    }
+
+    Ok(env.modify_var(name, |variable| {
+        set_property(variable, &path, value.clone())?;
+        Ok(value)
+    })?)
+}
+
+/// `a += v`, the target is evaluated once and read after the value.
+fn eval_compound_assignment(
+    assignee: &Stmt,
+    value: &Stmt,
+    operator: &str,
     env: &mut Environment,
-) -> Result<RuntimeValue> {
-    if let Stmt::Identifier(name) = assignee {
-        let value = evaluate(value, env)?;
+) -> Flow {
+    let mut path = vec![];
+    let name = eval_assignment_target(assignee, &mut path, env)?;
+    let value = eval(value, env)?;
+    let mode = env.arithmetic_mode();
 
-        return match env.assign_var(&name, value) {
-            Ok(v) => Ok(v),
-            Err(e) => Err(anyhow!(e)),
-        };
+    Ok(env.modify_var(name, |variable| {
+        let target = property_mut(variable, &path)?;
+        let result = eval_binary_operation(target.clone(), value, operator, mode)?;
+        *target = result.clone();
+        Ok(result)
+    })?)
+}
+
+/// Collects the keys of a member expression into `path` and returns the name
+/// of the variable it starts from.
+fn eval_assignment_target<'a>(
+    assignee: &'a Stmt,
+    path: &mut Vec<RuntimeValue>,
+    env: &mut Environment,
+) -> std::result::Result<&'a str, Interrupt> {
+    match &assignee.kind {
+        // `@y += 4` modifies `y` the same way `y += 4` does
+        StmtKind::Identifier(name) | StmtKind::Reference(name) => Ok(name),
+        StmtKind::MemberExpr {
+            object,
+            property,
+            computed,
+        } => {
+            let name = eval_assignment_target(object, path, env)?;
+            path.push(eval_property_key(property, *computed, env)?);
+            Ok(name)
+        }
+        _ => Err(with_span(anyhow!(EvalError::InvalidAssignment), assignee.span).into()),
     }
+}
 
-    Err(anyhow!(EvalError::InvalidAssignment))
+/// Writes `value` at the end of `path`, adding the last property to an
+/// object when it is missing.
+fn set_property(
+    target: &mut RuntimeValue,
+    path: &[RuntimeValue],
+    value: RuntimeValue,
+) -> Result<()> {
+    if let Some((RuntimeValue::String(key), parents)) = path.split_last() {
+        if let RuntimeValue::Object(properties) = property_mut(target, parents)? {
+            properties.insert(key.clone(), value);
+            return Ok(());
+        }
+    }
+
+    *property_mut(target, path)? = value;
+    Ok(())
+}
+
+/// The existing value at the end of `path`.
+fn property_mut<'a>(
+    target: &'a mut RuntimeValue,
+    path: &[RuntimeValue],
+) -> Result<&'a mut RuntimeValue> {
+    let Some((key, path)) = path.split_first() else {
+        return Ok(target);
+    };
+
+    let slot = match (target, key) {
+        (RuntimeValue::Object(properties), RuntimeValue::String(key)) => properties
+            .get_mut(key)
+            .ok_or_else(|| anyhow!(EvalError::MissingProperty(key.clone())))?,
+        (RuntimeValue::Array(elements), RuntimeValue::Integer(index)) => {
+            let position = array_index(*index, elements.len())?;
+            &mut elements[position]
+        }
+        (target, key) => {
+            return Err(anyhow!(EvalError::InvalidMemberAccess(
+                key.to_string(),
+                target.type_name()
+            )))
+        }
+    };
+
+    property_mut(slot, path)
 }
 
 fn eval_function_declaration(
-    name: String,
-    parameters: Vec<String>,
-    body: Vec<Stmt>,
+    name: &str,
+    parameters: &Rc<[Parameter]>,
+    body: &Rc<[Stmt]>,
     _is_const: bool,
     env: &mut Environment,
-) -> Result<RuntimeValue> {
+) -> Flow {
     let function = RuntimeValue::Function {
-        name: name.clone(),
-        parameters,
+        name: name.to_owned(),
+        parameters: parameters.clone(),
         declaration_env: env.clone(),
-        body,
+        body: body.clone(),
     };
 
-    env.declare_var(&name, function, true)
+    Ok(env.declare_var(name, function, true)?)
 }
 
 fn eval_variable_declaration(
     constant: bool,
-    identifier: String,
-    value: Option<Box<Stmt>>,
+    identifier: &str,
+    value: Option<&Stmt>,
     env: &mut Environment,
-) -> Result<RuntimeValue> {
+) -> Flow {
     let runtime_value = if let Some(value) = value {
-        evaluate(*value, env)?
+        eval(value, env)?
     } else {
         RuntimeValue::Null
     };
 
-    match env.declare_var(&identifier, runtime_value, constant) {
-        Ok(v) => Ok(v),
-        Err(e) => Err(anyhow!(e)),
+    Ok(env.declare_var(identifier, runtime_value, constant)?)
+}
+
+fn eval_identifier(v: &str, env: &mut Environment) -> Flow {
+    Ok(env.lookup_var(v)?)
+}
+
+fn evaluate_binary_expr(left: &Stmt, right: &Stmt, operator: &str, env: &mut Environment) -> Flow {
+    let lhs = eval(left, env)?;
+    let rhs = eval(right, env)?;
+
+    Ok(eval_binary_operation(
+        lhs,
+        rhs,
+        operator,
+        env.arithmetic_mode(),
+    )?)
+}
+
+fn eval_binary_operation(
+    lhs: RuntimeValue,
+    rhs: RuntimeValue,
+    operator: &str,
+    mode: ArithmeticMode,
+) -> Result<RuntimeValue> {
+    if let "==" | "!=" | "<" | "<=" | ">" | ">=" = operator {
+        return eval_comparison_expr(lhs, rhs, operator);
+    }
+
+    match (lhs, rhs) {
+        (lhs, rhs) if lhs.is_number() && rhs.is_number() => {
+            eval_numeric_binary_expr(lhs, rhs, operator, mode)
+        }
+        (RuntimeValue::String(lhs), rhs) if operator == "+" => {
+            Ok(RuntimeValue::String(lhs + &rhs.to_string()))
+        }
+        (lhs, RuntimeValue::String(rhs)) if operator == "+" => {
+            Ok(RuntimeValue::String(lhs.to_string() + &rhs))
+        }
+        (lhs, rhs) => Err(anyhow!(EvalError::InvalidOperands(
+            operator.to_owned(),
+            lhs.type_name(),
+            rhs.type_name()
+        ))),
     }
 }
 
-fn eval_identifier(v: String, env: &mut Environment) -> Result<RuntimeValue> {
-    match env.lookup_var(&v) {
-        Ok(v) => Ok(v),
-        Err(e) => Err(anyhow!(e)),
+/// Short-circuits on the left operand and yields whichever operand decided
+/// the result, so `a || b` is `a` when `a` is truthy and `b` otherwise.
+fn eval_logical_expr(left: &Stmt, right: &Stmt, operator: &str, env: &mut Environment) -> Flow {
+    let lhs = eval(left, env)?;
+
+    let decided = match operator {
+        "&&" => !lhs.is_truthy(),
+        "||" => lhs.is_truthy(),
+        _ => return Err(anyhow!(EvalError::InvalidOperator(operator.to_owned())).into()),
+    };
+
+    if decided {
+        Ok(lhs)
+    } else {
+        eval(right, env)
     }
 }
 
-fn evaluate_binary_expr(
-    left: Stmt,
-    right: Stmt,
-    operator: String,
-    env: &mut Environment,
+fn eval_unary_expr(operator: &str, operand: &Stmt, env: &mut Environment) -> Flow {
+    let value = eval(operand, env)?;
+
+    Ok(eval_unary_operation(
+        operator,
+        value,
+        env.arithmetic_mode(),
+    )?)
+}
+
+/// `-` negates numbers, `!` negates truthiness and `~` flips the bits of an
+/// integer, treating arbitrary precision integers as infinite two's complement.
+fn eval_unary_operation(
+    operator: &str,
+    value: RuntimeValue,
+    mode: ArithmeticMode,
 ) -> Result<RuntimeValue> {
-    if let RuntimeValue::Number(lhs) = evaluate(left, env)? {
-        if let RuntimeValue::Number(rhs) = evaluate(right, env)? {
-            return eval_numeric_binary_expr(lhs, rhs, operator);
-        }
+    match (operator, value) {
+        ("!", value) => Ok(RuntimeValue::Boolean(!value.is_truthy())),
+        ("-", RuntimeValue::Integer(i)) => eval_integer_negation(i, mode),
+        ("-", RuntimeValue::BigInt(i)) => Ok(i.neg().into()),
+        ("-", RuntimeValue::Float(f)) => Ok(RuntimeValue::Float(-f)),
+        ("~", RuntimeValue::Integer(i)) => Ok(RuntimeValue::Integer(!i)),
+        ("~", RuntimeValue::BigInt(i)) => Ok(i.neg().sub(&BigInt::from(1)).into()),
+        ("-" | "~", value) => Err(anyhow!(EvalError::InvalidOperand(
+            operator.to_owned(),
+            value.type_name()
+        ))),
+        _ => Err(anyhow!(EvalError::InvalidOperator(operator.to_owned()))),
+    }
+}
+
+/// Only `-i64::MIN` overflows, it is handled like binary arithmetic in `mode`.
+fn eval_integer_negation(value: i64, mode: ArithmeticMode) -> Result<RuntimeValue> {
+    use ArithmeticMode::*;
+
+    match (value.checked_neg(), mode) {
+        (Some(result), _) => Ok(RuntimeValue::Integer(result)),
+        (None, Promoting) => Ok(BigInt::from(value).neg().into()),
+        (None, Checked) => Err(anyhow!(EvalError::NegationOverflow(value))),
+        (None, Wrapping) => Ok(RuntimeValue::Integer(value.wrapping_neg())),
+        (None, Saturating) => Ok(RuntimeValue::Integer(value.saturating_neg())),
     }
+}
 
-    Ok(RuntimeValue::Null)
+/// Equality is defined between any two values, ordering only between two
+/// numbers or two strings.
+fn eval_comparison_expr(
+    lhs: RuntimeValue,
+    rhs: RuntimeValue,
+    operator: &str,
+) -> Result<RuntimeValue> {
+    let result = match operator {
+        "==" => lhs.equals(&rhs),
+        "!=" => !lhs.equals(&rhs),
+        _ => {
+            let ordered = (lhs.is_number() && rhs.is_number())
+                || matches!(
+                    (&lhs, &rhs),
+                    (RuntimeValue::String(_), RuntimeValue::String(_))
+                );
+            if !ordered {
+                return Err(anyhow!(EvalError::InvalidOperands(
+                    operator.to_owned(),
+                    lhs.type_name(),
+                    rhs.type_name()
+                )));
+            }
+
+            match (operator, lhs.compare(&rhs)) {
+                ("<", Some(ordering)) => ordering.is_lt(),
+                ("<=", Some(ordering)) => ordering.is_le(),
+                (">", Some(ordering)) => ordering.is_gt(),
+                (">=", Some(ordering)) => ordering.is_ge(),
+                _ => false,
+            }
+        }
+    };
+
+    Ok(RuntimeValue::Boolean(result))
 }
 
-fn eval_numeric_binary_expr(lhs: String, rhs: String, operator: String) -> Result<RuntimeValue> {
-    let lhs = lhs.parse::<i64>().unwrap();
-    let rhs = rhs.parse::<i64>().unwrap();
+/// Integers stay integers, with `/` truncating towards zero. As soon as either
+/// side is a float, both sides are promoted and the result is a float.
+/// Arbitrary precision integers are demoted back to `i64` whenever they fit.
+fn eval_numeric_binary_expr(
+    lhs: RuntimeValue,
+    rhs: RuntimeValue,
+    operator: &str,
+    mode: ArithmeticMode,
+) -> Result<RuntimeValue> {
+    if let (RuntimeValue::Integer(lhs), RuntimeValue::Integer(rhs)) = (&lhs, &rhs) {
+        return eval_integer_binary_expr(*lhs, *rhs, operator, mode);
+    }
+
+    if let (Some(lhs), Some(rhs)) = (lhs.as_big_int(), rhs.as_big_int()) {
+        return eval_big_int_binary_expr(lhs, rhs, operator);
+    }
+
+    let (Some(lhs), Some(rhs)) = (lhs.as_float(), rhs.as_float()) else {
+        return Err(anyhow!(EvalError::InvalidOperands(
+            operator.to_owned(),
+            lhs.type_name(),
+            rhs.type_name()
+        )));
+    };
 
-    let result = match &*operator {
+    let result = match operator {
         "+" => lhs + rhs,
         "-" => lhs - rhs,
         "*" => lhs * rhs,
         "/" => lhs / rhs,
         "%" => lhs % rhs,
         _ => {
-            return Err(anyhow!(EvalError::InvalidOperator(operator)));
+            return Err(anyhow!(EvalError::InvalidOperator(operator.to_owned())));
+        }
+    };
+
+    Ok(RuntimeValue::Float(result))
+}
+
+/// Division by zero is an error regardless of `mode`.
+fn eval_integer_binary_expr(
+    lhs: i64,
+    rhs: i64,
+    operator: &str,
+    mode: ArithmeticMode,
+) -> Result<RuntimeValue> {
+    if rhs == 0 && (operator == "/" || operator == "%") {
+        return Err(anyhow!(EvalError::DivisionByZero));
+    }
+
+    use ArithmeticMode::*;
+
+    let result = match (operator, mode) {
+        ("+", Promoting | Checked) => lhs.checked_add(rhs),
+        ("+", Wrapping) => Some(lhs.wrapping_add(rhs)),
+        ("+", Saturating) => Some(lhs.saturating_add(rhs)),
+        ("-", Promoting | Checked) => lhs.checked_sub(rhs),
+        ("-", Wrapping) => Some(lhs.wrapping_sub(rhs)),
+        ("-", Saturating) => Some(lhs.saturating_sub(rhs)),
+        ("*", Promoting | Checked) => lhs.checked_mul(rhs),
+        ("*", Wrapping) => Some(lhs.wrapping_mul(rhs)),
+        ("*", Saturating) => Some(lhs.saturating_mul(rhs)),
+        ("/", Promoting | Checked) => lhs.checked_div(rhs),
+        ("/", Wrapping) => Some(lhs.wrapping_div(rhs)),
+        ("/", Saturating) => Some(lhs.saturating_div(rhs)),
+        // `i64::MIN % -1` is the only overflowing remainder and is 0 mathematically
+        ("%", _) => Some(lhs.wrapping_rem(rhs)),
+        _ => {
+            return Err(anyhow!(EvalError::InvalidOperator(operator.to_owned())));
+        }
+    };
+
+    match result {
+        Some(result) => Ok(RuntimeValue::Integer(result)),
+        None if mode == Promoting => {
+            eval_big_int_binary_expr(BigInt::from(lhs), BigInt::from(rhs), operator)
+        }
+        None => Err(anyhow!(EvalError::IntegerOverflow {
+            lhs,
+            operator: operator.to_owned(),
+            rhs,
+        })),
+    }
+}
+
+fn eval_big_int_binary_expr(lhs: BigInt, rhs: BigInt, operator: &str) -> Result<RuntimeValue> {
+    let result = match operator {
+        "+" => lhs.add(&rhs),
+        "-" => lhs.sub(&rhs),
+        "*" => lhs.mul(&rhs),
+        "/" | "%" => {
+            let Some((quotient, remainder)) = lhs.div_rem(&rhs) else {
+                return Err(anyhow!(EvalError::DivisionByZero));
+            };
+            if operator == "/" {
+                quotient
+            } else {
+                remainder
+            }
+        }
+        _ => {
+            return Err(anyhow!(EvalError::InvalidOperator(operator.to_owned())));
         }
     };
 
-    Ok(RuntimeValue::Number(result.to_string()))
+    Ok(RuntimeValue::from(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::SpannedError, parser::Parser};
+
+    fn run(src: &str) -> Result<RuntimeValue> {
+        run_with(src, ArithmeticMode::default())
+    }
+
+    fn run_with(src: &str, mode: ArithmeticMode) -> Result<RuntimeValue> {
+        let mut env = Environment::new();
+        env.set_arithmetic_mode(mode);
+
+        let program = Parser::new().produce_ast(src.to_owned())?;
+        evaluate(StmtKind::Program(program).into(), &mut env)
+    }
+
+    #[test]
+    fn string_concatenation() {
+        let result = run(r#"let name = "selve"; "hello " + name + "\n" + 42"#);
+
+        assert_eq!(
+            RuntimeValue::String("hello selve\n42".to_owned()),
+            result.unwrap()
+        );
+    }
+
+    #[test]
+    fn string_invalid_operator() {
+        let error = run(r#""foo" * 2"#).unwrap_err();
+
+        assert_eq!(
+            "Cannot apply operator * to string and integer",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn invalid_operands() {
+        let cases = [
+            ("true + 1", "Cannot apply operator + to boolean and integer"),
+            ("[1] + [2]", "Cannot apply operator + to array and array"),
+            ("null * 2", "Cannot apply operator * to null and integer"),
+            (
+                "let o = {}; o - o",
+                "Cannot apply operator - to object and object",
+            ),
+        ];
+
+        for (src, message) in cases {
+            let error = run(src).unwrap_err();
+            assert_eq!(message, error.to_string(), "{src}");
+        }
+    }
+
+    #[test]
+    fn comments() {
+        let cases = [
+            ("let x = 1; // c", "1"),
+            ("let x = 1 /* a */ + /* b */ 2; x", "3"),
+            ("fn f() {\n  // c\n  1 // d\n}; f()", "1"),
+            ("{ a: 1, // c\n  b: /* d */ 2 }", "{ a: 1, b: 2 }"),
+            ("/* a\n   b */", "null"),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap().to_string(), "{src}");
+        }
+    }
+
+    #[test]
+    fn string_interpolation() {
+        let result =
+            run(r#"let name = "selve"; let count = 2; "hi {name}, {count + 1} {"{count}"}""#);
+
+        assert_eq!(
+            RuntimeValue::String("hi selve, 3 2".to_owned()),
+            result.unwrap()
+        );
+    }
+
+    #[test]
+    fn object_display() {
+        let result = run("let d = 4; let c = 3; { d, c, b: { z: 1, y: 2 }, a: 1 }");
+
+        assert_eq!(
+            "{ a: 1, b: { y: 2, z: 1 }, c: 3, d: 4 }",
+            result.unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn numeric_promotion() {
+        let cases = [
+            ("7 / 2", RuntimeValue::Integer(3)),
+            ("7 % 4 * 3", RuntimeValue::Integer(9)),
+            ("7 / 2.0", RuntimeValue::Float(3.5)),
+            ("1.5 + 1", RuntimeValue::Float(2.5)),
+            ("1e3 - 1", RuntimeValue::Float(999.0)),
+            ("7.5 % 2", RuntimeValue::Float(1.5)),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap(), "{src}");
+        }
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        let max = i64::MAX;
+        let cases = [
+            ("1 / 0".to_owned(), "Division by zero"),
+            ("1 % 0".to_owned(), "Division by zero"),
+            (
+                format!("{max} + 1"),
+                "Integer overflow in 9223372036854775807 + 1",
+            ),
+            (
+                format!("0 - {max} - 2"),
+                "Integer overflow in -9223372036854775807 - 2",
+            ),
+            (
+                format!("{max} * 2"),
+                "Integer overflow in 9223372036854775807 * 2",
+            ),
+        ];
+
+        for (src, message) in cases {
+            let error = run_with(&src, ArithmeticMode::Checked).unwrap_err();
+            assert_eq!(message, error.to_string(), "{src}");
+        }
+    }
+
+    #[test]
+    fn remainder_of_min_by_minus_one() {
+        use ArithmeticMode::*;
+
+        for mode in [Promoting, Checked, Wrapping, Saturating] {
+            assert_eq!(
+                RuntimeValue::Integer(0),
+                run_with("(-9223372036854775807 - 1) % -1", mode).unwrap(),
+                "{mode:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn wrapping_and_saturating_arithmetic() {
+        let src = format!("{} * 2 + 3", i64::MAX);
+
+        assert_eq!(
+            RuntimeValue::Integer(1),
+            run_with(&src, ArithmeticMode::Wrapping).unwrap()
+        );
+        assert_eq!(
+            RuntimeValue::Integer(i64::MAX),
+            run_with(&src, ArithmeticMode::Saturating).unwrap()
+        );
+        assert_eq!(
+            "Division by zero",
+            run_with("1 / 0", ArithmeticMode::Wrapping)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn big_int_promotion() {
+        let max = i64::MAX;
+        let cases = [
+            (format!("{max} + 1"), "9223372036854775808"),
+            (
+                format!("{max} * {max}"),
+                "85070591730234615847396907784232501249",
+            ),
+            (format!("({max} + 1) - 1"), "9223372036854775807"),
+            (
+                "123456789012345678901234567890 / 1000000000 % 1000".to_owned(),
+                "901",
+            ),
+            ("99999999999999999999 * 0 + 1".to_owned(), "1"),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(&src).unwrap().to_string(), "{src}");
+        }
+
+        // results that fit again are demoted to plain integers
+        assert_eq!(
+            RuntimeValue::Integer(max),
+            run(&format!("({max} + 1) - 1")).unwrap()
+        );
+        assert_eq!(
+            RuntimeValue::Float(1e20),
+            run("100000000000000000000 * 1.0").unwrap()
+        );
+        assert_eq!(
+            "Division by zero",
+            run("99999999999999999999 % 0").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn radix_literals() {
+        assert_eq!(
+            RuntimeValue::Integer(1_000_758),
+            run("0xFF + 0b1010 + 0o755 + 1_000_000").unwrap()
+        );
+        assert_eq!(
+            "1208925819614629174706175",
+            run("0xFFFF_FFFF_FFFF_FFFF_FFFF").unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn comparisons() {
+        let truthy = [
+            "1 < 2",
+            "2 <= 2",
+            "3 > 2.5",
+            "1.5 >= 1",
+            "1 == 1.0",
+            "1 != 2",
+            "99999999999999999999 > 9223372036854775807",
+            "99999999999999999999 == 99999999999999999999",
+            "\"abc\" < \"abd\"",
+            "\"a\" == \"a\"",
+            "null == null",
+            "true == true",
+            "1 + 1 == 2",
+            "let a = { a: 1, b: { c: \"d\" } }; let b = { b: { c: \"d\" }, a: 1.0 }; a == b",
+            "print == print",
+            "print != time",
+        ];
+        for src in truthy {
+            assert_eq!(RuntimeValue::Boolean(true), run(src).unwrap(), "{src}");
+        }
+
+        let falsy = [
+            "2 < 1",
+            "1 == \"1\"",
+            "null == false",
+            "let a = { a: 1 }; let b = { a: 1, b: 2 }; a == b",
+            "let a = {}; a == null",
+        ];
+        for src in falsy {
+            assert_eq!(RuntimeValue::Boolean(false), run(src).unwrap(), "{src}");
+        }
+    }
+
+    #[test]
+    fn function_equality() {
+        let src = "fn add(a, b) { a + b; }; let f = add; f == add";
+        assert_eq!(RuntimeValue::Boolean(true), run(src).unwrap());
+
+        let src = "fn add(a, b) { a + b; }; fn sub(a, b) { a - b; }; add == sub";
+        assert_eq!(RuntimeValue::Boolean(false), run(src).unwrap());
+
+        // Closures from different calls capture different scopes
+        let src = "fn adder(n) { fn(x) { x + n } }; adder(1) == adder(2)";
+        assert_eq!(RuntimeValue::Boolean(false), run(src).unwrap());
+        let src = "fn adder(n) { fn(x) { x + n } }; adder(1) == adder(1)";
+        assert_eq!(RuntimeValue::Boolean(false), run(src).unwrap());
+        let src = "fn adder(n) { fn(x) { x + n } }; let f = adder(1); let g = f; f == g";
+        assert_eq!(RuntimeValue::Boolean(true), run(src).unwrap());
+
+        // Identical source is not the same declaration
+        let src = "let f = fn(x) { x }; let g = fn(x) { x }; f == g";
+        assert_eq!(RuntimeValue::Boolean(false), run(src).unwrap());
+        let src = "fn mk() { fn(x) { x } }; mk() == mk()";
+        assert_eq!(RuntimeValue::Boolean(false), run(src).unwrap());
+        let src = "let f = fn() {}; [f] == [f]";
+        assert_eq!(RuntimeValue::Boolean(true), run(src).unwrap());
+    }
+
+    #[test]
+    fn invalid_ordering() {
+        let error = run("\"a\" < 1").unwrap_err();
+        assert_eq!(
+            "Cannot apply operator < to string and integer",
+            error.to_string()
+        );
+        assert!(run("null >= null").is_err());
+    }
+
+    #[test]
+    fn logical_operators() {
+        let cases = [
+            ("true && false", RuntimeValue::Boolean(false)),
+            ("false || true", RuntimeValue::Boolean(true)),
+            ("1 < 2 && 2 < 3", RuntimeValue::Boolean(true)),
+            (
+                "null || \"default\"",
+                RuntimeValue::String("default".to_owned()),
+            ),
+            ("0 && 1", RuntimeValue::Integer(0)),
+            ("\"a\" && 2", RuntimeValue::Integer(2)),
+            ("!null", RuntimeValue::Boolean(true)),
+            ("!!\"\"", RuntimeValue::Boolean(false)),
+            ("let o = {}; !o", RuntimeValue::Boolean(false)),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap(), "{src}");
+        }
+    }
+
+    #[test]
+    fn short_circuit() {
+        // The right side would fail to resolve if it was evaluated
+        assert_eq!(
+            RuntimeValue::Boolean(false),
+            run("false && missing").unwrap()
+        );
+        assert_eq!(RuntimeValue::Boolean(true), run("true || missing").unwrap());
+        assert!(run("true && missing").is_err());
+    }
+
+    #[test]
+    fn unary_operators() {
+        let cases = [
+            ("let x = -5; x", RuntimeValue::Integer(-5)),
+            ("1 - -2", RuntimeValue::Integer(3)),
+            ("--3", RuntimeValue::Integer(3)),
+            ("-2 * 3", RuntimeValue::Integer(-6)),
+            ("-1.5", RuntimeValue::Float(-1.5)),
+            ("~5", RuntimeValue::Integer(-6)),
+            ("~-1", RuntimeValue::Integer(0)),
+            ("!-0", RuntimeValue::Boolean(true)),
+            ("-9223372036854775808", RuntimeValue::Integer(i64::MIN)),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap(), "{src}");
+        }
+
+        assert_eq!(
+            "9223372036854775808",
+            run("-(-9223372036854775807 - 1)").unwrap().to_string()
+        );
+        assert_eq!(
+            "-100000000000000000001",
+            run("~100000000000000000000").unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn unary_errors() {
+        let min = "-(-9223372036854775807 - 1)";
+        assert_eq!(
+            "Integer overflow in -(-9223372036854775808)",
+            run_with(min, ArithmeticMode::Checked)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            RuntimeValue::Integer(i64::MIN),
+            run_with(min, ArithmeticMode::Wrapping).unwrap()
+        );
+        assert_eq!(
+            RuntimeValue::Integer(i64::MAX),
+            run_with(min, ArithmeticMode::Saturating).unwrap()
+        );
+
+        assert_eq!(
+            "Cannot apply operator - to string",
+            run("-\"a\"").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Cannot apply operator ~ to float",
+            run("~1.5").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn if_else() {
+        let src = "
+            fn classify(n) {
+                if n < 0 {
+                    \"negative\"
+                } else if n == 0 {
+                    \"zero\"
+                } else {
+                    \"positive\"
+                }
+            }
+            classify(-3) + \" \" + classify(0) + \" \" + classify(7)
+        ";
+        assert_eq!(
+            RuntimeValue::String("negative zero positive".to_owned()),
+            run(src).unwrap()
+        );
+
+        assert_eq!(RuntimeValue::Null, run("if false { 1 }").unwrap());
+        assert_eq!(
+            RuntimeValue::Integer(2),
+            run("if 0 { 1 } else { 2 }").unwrap()
+        );
+    }
+
+    #[test]
+    fn if_scope() {
+        let src = "let x = 1; if true { let x = 2; x } else { 0 }";
+        assert_eq!(RuntimeValue::Integer(2), run(src).unwrap());
+
+        let src = "let x = 1; if true { let y = 2; x = x + y; }; x";
+        assert_eq!(RuntimeValue::Integer(3), run(src).unwrap());
+
+        let error = run("if true { let y = 2; }; y").unwrap_err();
+        assert_eq!("Cannot resolve y since it doesnt exist", error.to_string());
+    }
+
+    #[test]
+    fn while_loop() {
+        let src = "
+            let i = 0;
+            let sum = 0;
+            while i < 10 {
+                i = i + 1;
+                if i % 2 == 0 { continue }
+                if i > 7 { break }
+                sum = sum + i;
+            }
+            sum
+        ";
+        assert_eq!(RuntimeValue::Integer(1 + 3 + 5 + 7), run(src).unwrap());
+        assert_eq!(RuntimeValue::Null, run("while false { 1 }").unwrap());
+    }
+
+    #[test]
+    fn loop_with_break_value() {
+        let src = "
+            let n = 1;
+            let power = loop {
+                if n > 1000 { break n }
+                n = n * 2;
+            };
+            power
+        ";
+        assert_eq!(RuntimeValue::Integer(1024), run(src).unwrap());
+
+        let src =
+            "let i = 0; loop { i = i + 1; let j = loop { break i * 10 }; if j == 30 { break j } }";
+        assert_eq!(RuntimeValue::Integer(30), run(src).unwrap());
+    }
+
+    #[test]
+    fn loop_control_errors() {
+        let cases = [
+            ("break", "`break` outside of a loop", Span::new(0, 5, 1, 1)),
+            (
+                "if true { continue }",
+                "`continue` outside of a loop",
+                Span::new(10, 18, 1, 11),
+            ),
+            (
+                "while true { break 1 }",
+                "`break` with a value inside a `while` loop",
+                Span::new(13, 20, 1, 14),
+            ),
+            (
+                "fn f() { break }; loop { f() }",
+                "`break` outside of a loop",
+                Span::new(9, 14, 1, 10),
+            ),
+        ];
+
+        for (src, message, span) in cases {
+            let error = run(src).unwrap_err();
+            assert_eq!(message, error.to_string(), "{src}");
+            let error = error.downcast_ref::<SpannedError>().unwrap();
+            assert_eq!(span, error.span, "{src}");
+        }
+    }
+
+    #[test]
+    fn for_loop() {
+        let cases = [
+            ("let sum = 0; for i in 0..5 { sum = sum + i }; sum", "10"),
+            ("let sum = 0; for i in 1..=5 { sum = sum + i }; sum", "15"),
+            ("let n = 0; for i in 5..0 { n = n + 1 }; n", "0"),
+            (
+                "let s = \"\"; for x in [1, \"a\", 2.5] { s = s + x + \" \" }; s",
+                "1 a 2.5 ",
+            ),
+            ("let s = \"\"; for c in \"héllo\" { s = c + s }; s", "olléh"),
+            (
+                "let s = \"\"; for key in { b: 1, a: 2, c: 3 } { s = s + key }; s",
+                "abc",
+            ),
+            (
+                "let found = null; for i in 0..100 { if i * i > 50 { found = i; break } }; found",
+                "8",
+            ),
+            (
+                "let n = 0; for i in 0..10 { if i % 2 == 0 { continue } n = n + i }; n",
+                "25",
+            ),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap().to_string(), "{src}");
+        }
+    }
+
+    #[test]
+    fn for_loop_scope() {
+        // Every iteration declares its own binding
+        let src =
+            "let kept = null; for i in 0..3 { fn get() { i } if i == 1 { kept = get } }; kept()";
+        assert_eq!(RuntimeValue::Integer(1), run(src).unwrap());
+
+        let error = run("for i in 0..3 { }; i").unwrap_err();
+        assert_eq!("Cannot resolve i since it doesnt exist", error.to_string());
+    }
+
+    #[test]
+    fn for_loop_errors() {
+        let cases = [
+            ("for x in 5 { }", "Cannot iterate over integer"),
+            (
+                "for x in 0..1.5 { }",
+                "Range bounds must be integers, got float",
+            ),
+            (
+                "for x in 0..9223372036854775808 { }",
+                "Range bound 9223372036854775808 does not fit in a 64-bit integer",
+            ),
+            (
+                "for x in [1] { break x }",
+                "`break` with a value inside a `for` loop",
+            ),
+        ];
+
+        for (src, message) in cases {
+            assert_eq!(message, run(src).unwrap_err().to_string(), "{src}");
+        }
+    }
+
+    #[test]
+    fn arrays_and_ranges() {
+        assert_eq!(
+            "[1, [2, 3], []]",
+            run("[1, [2, 3], []]").unwrap().to_string()
+        );
+        assert_eq!("0..=10", run("0..=5 * 2").unwrap().to_string());
+        assert_eq!(
+            RuntimeValue::Boolean(true),
+            run("let a = [1, 2]; a == [1, 2.0]").unwrap()
+        );
+        assert_eq!(
+            RuntimeValue::Boolean(false),
+            run("let a = [1, 2]; a == [2, 1]").unwrap()
+        );
+        assert_eq!(
+            RuntimeValue::Boolean(true),
+            run("let r = 0..3; r == (0..3)").unwrap()
+        );
+    }
+
+    #[test]
+    fn return_statement() {
+        let src = "
+            fn find(items, target) {
+                let index = 0;
+                for item in items {
+                    if item == target {
+                        return index;
+                    }
+                    index = index + 1;
+                }
+                -1
+            }
+            find([4, 8, 15, 16], 15) * 10 + find([], 1)
+        ";
+        assert_eq!(RuntimeValue::Integer(19), run(src).unwrap());
+
+        let src = "fn f() { loop { while true { return \"out\" } } }; f()";
+        assert_eq!(RuntimeValue::String("out".to_owned()), run(src).unwrap());
+
+        let src = "fn f() { return; 1 }; f()";
+        assert_eq!(RuntimeValue::Null, run(src).unwrap());
+    }
+
+    #[test]
+    fn error_spans_keep_their_source() {
+        let mut env = Environment::new();
+        let mut parser = Parser::new();
+
+        for (source, src) in ["fn f() {\n  1 / 0 }", "f()"].into_iter().enumerate() {
+            let program = parser.produce_ast_from(src.to_owned(), source).unwrap();
+            let result = evaluate(StmtKind::Program(program).into(), &mut env);
+
+            if source == 1 {
+                let error = result.unwrap_err();
+                let error = error.downcast_ref::<SpannedError>().unwrap();
+                let span = Span {
+                    source: 0,
+                    ..Span::new(11, 16, 2, 3)
+                };
+                assert_eq!(span, error.span);
+            }
+        }
+    }
+
+    #[test]
+    fn return_outside_function() {
+        // The parser rejects these, a tree built by hand still fails at runtime
+        let span = Span::new(16, 22, 1, 17);
+        let program = StmtKind::ForLoop {
+            variable: "i".to_owned(),
+            iterable: Box::new(
+                StmtKind::ArrayLiteral(vec![StmtKind::IntegerLiteral(0).into()]).into(),
+            ),
+            body: vec![Stmt::new(StmtKind::Return(None), span)],
+        };
+
+        let error = evaluate(program.into(), &mut Environment::new()).unwrap_err();
+        assert_eq!("`return` outside of a function", error.to_string());
+        let error = error.downcast_ref::<SpannedError>().unwrap();
+        assert_eq!(span, error.span);
+    }
+
+    #[test]
+    fn recursion() {
+        let src = "fn fact(n) { if n <= 1 { 1 } else { n * fact(n - 1) } }; fact(25)";
+        assert_eq!("15511210043330985984000000", run(src).unwrap().to_string());
+
+        let src = "
+            fn is_even(n) { if n == 0 { true } else { is_odd(n - 1) } }
+            fn is_odd(n) { if n == 0 { false } else { is_even(n - 1) } }
+            is_even(10) && is_odd(7)
+        ";
+        assert_eq!(RuntimeValue::Boolean(true), run(src).unwrap());
+
+        let src = "
+            fn outer() {
+                fn fib(n) { if n < 2 { return n } fib(n - 1) + fib(n - 2) }
+                fib(15)
+            }
+            outer()
+        ";
+        assert_eq!(RuntimeValue::Integer(610), run(src).unwrap());
+    }
+
+    #[test]
+    fn call_depth_limit() {
+        // Runs with the stack the interpreter is given outside of tests
+        let interpreter = std::thread::Builder::new().stack_size(STACK_SIZE);
+        let message = interpreter
+            .spawn(|| {
+                let count_down = "fn f(n) { if n == 0 { 0 } else { f(n - 1) } }; f(999)";
+                assert_eq!(RuntimeValue::Integer(0), run(count_down).unwrap());
+
+                let error = run("fn f(n) { f(n + 1) }; f(0)").unwrap_err();
+                let message = error.to_string();
+
+                // The depth is unwound along with the error
+                assert_eq!(RuntimeValue::Integer(0), run(count_down).unwrap());
+                message
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!("Calling f exceeds the maximum call depth of 1000", message);
+    }
+
+    #[test]
+    fn closures_capture_by_reference() {
+        let src = "
+            fn counter() {
+                let n = 0;
+                fn next() { n = n + 1 }
+                next
+            }
+            let a = counter();
+            let b = counter();
+            a(); a(); b();
+            a() * 10 + b()
+        ";
+        assert_eq!(RuntimeValue::Integer(32), run(src).unwrap());
+
+        let src = "let x = 1; fn get() { x }; x = 5; get()";
+        assert_eq!(RuntimeValue::Integer(5), run(src).unwrap());
+    }
+
+    #[test]
+    fn anonymous_functions() {
+        let cases = [
+            ("fn apply(f, x) { f(x) }; apply(fn(x) { x * 2 }, 21)", "42"),
+            ("fn adder(n) { fn(x) { x + n } }; adder(5)(10)", "15"),
+            ("let square = fn(x) { x * x }; square(square(3))", "81"),
+            (
+                "fn twice(f) { fn(x) { f(f(x)) } }; let inc = fn(x) { x + 1 }; twice(twice(inc))(0)",
+                "4",
+            ),
+            ("fn(x) { x }", "<anonymous>()"),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap().to_string(), "{src}");
+        }
+    }
+
+    #[test]
+    fn default_parameters() {
+        let cases = [
+            ("fn f(x, y = 10) { x + y }; f(1)", "11"),
+            ("fn f(x, y = 10) { x + y }; f(1, 2)", "3"),
+            ("fn f(x, y = x * 2) { x + y }; f(4)", "12"),
+            ("let n = 1; fn f(x = n) { x }; n = 7; f()", "7"),
+            ("let g = fn(a = \"hi\") { a }; g()", "hi"),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap().to_string(), "{src}");
+        }
+    }
+
+    #[test]
+    fn arity_mismatch() {
+        let cases = [
+            ("fn f(x, y) { x }; f(1)", "f expects 2 arguments but got 1"),
+            ("fn f(x) { x }; f(1, 2)", "f expects 1 argument but got 2"),
+            ("fn f() { 0 }; f(1)", "f expects 0 arguments but got 1"),
+            (
+                "fn f(x, y = 1, z = 2) { x }; f()",
+                "f expects 1 to 3 arguments but got 0",
+            ),
+            (
+                "let g = fn(x) { x }; g()",
+                "<anonymous> expects 1 argument but got 0",
+            ),
+        ];
+
+        for (src, message) in cases {
+            let error = run(src).unwrap_err();
+            assert_eq!(message, error.to_string(), "{src}");
+        }
+
+        let error = run("fn f(x) { x }\nf()").unwrap_err();
+        let error = error.downcast_ref::<SpannedError>().unwrap();
+        assert_eq!(Span::new(14, 17, 2, 1), error.span);
+    }
+
+    #[test]
+    fn variadics() {
+        let cases = [
+            ("fn f(...xs) { xs }; f()", "[]"),
+            ("fn f(a, ...xs) { xs }; f(1, 2, 3)", "[2, 3]"),
+            ("fn f(a, b = 2, ...xs) { [a, b, xs] }; f(1)", "[1, 2, []]"),
+            (
+                "fn f(a, b, c) { a + b + c }; let xs = [2, 3]; f(1, ...xs)",
+                "6",
+            ),
+            ("fn f(...xs) { xs }; f(...0..3, 3, ...[])", "[0, 1, 2, 3]"),
+            (
+                "fn f(...xs) { xs }; let g = fn(...xs) { f(0, ...xs) }; g(1, 2)",
+                "[0, 1, 2]",
+            ),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap().to_string(), "{src}");
+        }
+
+        let error = run("fn f(a, b, ...xs) { a }; f(1)").unwrap_err();
+        assert_eq!(
+            "f expects at least 2 arguments but got 1",
+            error.to_string()
+        );
+
+        let error = run("fn f(a) { a }; f(1, ...[2])").unwrap_err();
+        assert_eq!("f expects 1 argument but got 2", error.to_string());
+
+        let error = run("print(...5)").unwrap_err();
+        assert_eq!("Cannot iterate over integer", error.to_string());
+        let error = error.downcast_ref::<SpannedError>().unwrap();
+        assert_eq!(Span::new(9, 10, 1, 10), error.span);
+    }
+
+    #[test]
+    fn named_arguments() {
+        let cases = [
+            ("fn f(a, b) { a - b }; f(b: 1, a: 3)", "2"),
+            (
+                "fn f(a, b = 2, c = 3) { [a, b, c] }; f(1, c: 4)",
+                "[1, 2, 4]",
+            ),
+            ("fn f(a, b = a) { b }; f(a: 5)", "5"),
+            ("fn f(a, ...xs) { [a, xs] }; f(a: 1)", "[1, []]"),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap().to_string(), "{src}");
+        }
+
+        let cases = [
+            ("fn f(a) { a }; f(b: 1)", "f has no parameter named b"),
+            (
+                "fn f(...xs) { xs }; f(xs: 1)",
+                "f has no parameter named xs",
+            ),
+            (
+                "fn f(a, b) { a }; f(1, a: 2)",
+                "Argument a is given more than once",
+            ),
+            (
+                "fn f(a, b) { a }; f(a: 1, a: 2)",
+                "Argument a is given more than once",
+            ),
+            (
+                "fn f(a, b = 1) { a }; f(b: 2)",
+                "f is missing an argument for a",
+            ),
+            ("print(x: 1)", "print has no parameter named x"),
+            (
+                "let p = print; p(1, y: 2)",
+                "print has no parameter named y",
+            ),
+        ];
+
+        for (src, message) in cases {
+            let error = run(src).unwrap_err();
+            assert_eq!(message, error.to_string(), "{src}");
+        }
+
+        let error = run("fn f(a) { a }\nf(b: 1)").unwrap_err();
+        let error = error.downcast_ref::<SpannedError>().unwrap();
+        assert_eq!(Span::new(19, 20, 2, 6), error.span);
+    }
+
+    #[test]
+    fn member_access() {
+        let cases = [
+            ("let o = { a: 1, b: { c: 2 } }; o.a + o.b.c", "3"),
+            (r#"let o = { a: 1 }; let k = "a"; o["a"] + o[k]"#, "2"),
+            ("let xs = [1, [2, 3]]; xs[1][0]", "2"),
+            ("let o = { f: fn(x) { x * 2 } }; o.f(4)", "8"),
+            ("let o = { xs: [{ n: 7 }] }; o.xs[0].n", "7"),
+            ("fn f() { { a: 1 } }; f().a", "1"),
+            ("fn f() { [4, 5] }; f()[1]", "5"),
+            ("let o = { f: fn(x) { { g: x * 2 } } }; o.f(1).g", "2"),
+            (
+                "let o = { f: fn() { [fn(x) { x + 1 }] } }; o.f()[0](1)",
+                "2",
+            ),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap().to_string(), "{src}");
+        }
+
+        let cases = [
+            ("let o = { a: 1 }; o.b", "Object has no property b"),
+            ("let n = 5; n.a", "Cannot access property a of integer"),
+            (
+                "let o = { a: 1 }; o[0]",
+                "Cannot access property 0 of object",
+            ),
+            (
+                "let xs = [1]; xs[1]",
+                "Index 1 is out of bounds for an array of length 1",
+            ),
+            (
+                "let xs = [1]; xs[-1]",
+                "Index -1 is out of bounds for an array of length 1",
+            ),
+        ];
+
+        for (src, message) in cases {
+            let error = run(src).unwrap_err();
+            assert_eq!(message, error.to_string(), "{src}");
+        }
+
+        let error = run("let o = {};\no.a").unwrap_err();
+        let error = error.downcast_ref::<SpannedError>().unwrap();
+        assert_eq!(Span::new(12, 15, 2, 1), error.span);
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let cases = [
+            ("let x = 5; x += 2; x -= 1; x *= 4; x /= 3; x %= 5; x", "3"),
+            ("let s = \"a\"; s += \"b\"", "ab"),
+            ("let o = { n: { m: 1 } }; o.n.m += 41; o", "{ n: { m: 42 } }"),
+            (
+                "let i = 0; fn next() { i += 1; i - 1 }; let xs = [1, 2]; xs[next()] += 10; [xs, i]",
+                "[[11, 2], 1]",
+            ),
+            ("let x = 1; let y = x += 1; [x, y]", "[2, 2]"),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap().to_string(), "{src}");
+        }
+
+        let cases = [
+            ("const x = 1; x += 1", "Cannot reassign to constant x"),
+            ("let o = {}; o.a += 1", "Object has no property a"),
+            ("let x = 1; x /= 0", "Division by zero"),
+            ("1 += 1", "Invalid assignment"),
+        ];
+
+        for (src, message) in cases {
+            let error = run(src).unwrap_err();
+            assert_eq!(message, error.to_string(), "{src}");
+        }
+    }
+
+    #[test]
+    fn references() {
+        let cases = [
+            ("let y = 1; @y += 4; y", "5"),
+            ("fn bump(n) { n += 4 }; let y = 1; bump(@y); y", "5"),
+            (
+                "fn set(target, value) { target = value }; let s = \"a\"; set(@s, \"b\"); s",
+                "b",
+            ),
+            ("fn set(o) { o.a = 2 }; let o = { a: 1 }; set(@o); o.a", "2"),
+            (
+                "fn outer(n) { inner(@n) }; fn inner(m) { m *= 10 }; let y = 2; outer(@y); y",
+                "20",
+            ),
+            ("let y = 1; let r = @y; y = 3; r", "3"),
+            ("fn f(...xs) { xs }; let y = 1; f(@y)", "[1]"),
+            (
+                "fn f(...xs) { xs[0] = 5; xs }; let y = 1; let xs = f(@y); [xs, y]",
+                "[[5], 1]",
+            ),
+            (
+                "fn f(n) { [n + 1, n == 1, { r: n }, \"v={n}\"] }; let y = 1; f(@y)",
+                "[2, true, { r: 1 }, v=1]",
+            ),
+            (
+                "let y = 1; let a = @y; let b = @a; b = 5; [y, a, b]",
+                "[5, 5, 5]",
+            ),
+            (
+                "let y = 1; fn get(r) { fn() { r } }; let g = get(@y); y = 9; g()",
+                "9",
+            ),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap().to_string(), "{src}");
+        }
+
+        let cases = [
+            ("const y = 1; @y += 4", "Cannot reassign to constant y"),
+            (
+                "const y = 1; fn f(n) { n }; f(@y)",
+                "Cannot take a reference to constant y",
+            ),
+            (
+                "fn f(n) { n }; f(@f)",
+                "Cannot take a reference to constant f",
+            ),
+            (
+                "let y = 1; let r = 2; r = @y",
+                "Reference @y cannot be used here",
+            ),
+            (
+                "let y = 1; print(@y + 1)",
+                "Reference @y cannot be used here",
+            ),
+            (
+                "let r = @missing",
+                "Cannot resolve missing since it doesnt exist",
+            ),
+        ];
+
+        for (src, message) in cases {
+            let error = run(src).unwrap_err();
+            assert_eq!(message, error.to_string(), "{src}");
+        }
+    }
+
+    #[test]
+    fn property_assignment() {
+        let cases = [
+            ("let o = { a: 1 }; o.a = 2; o.a", "2"),
+            ("let o = {}; o.a = 1; o[\"b\"] = 2; o.a + o.b", "3"),
+            (
+                "let config = { server: { port: 80 } }; config.server.port = 9000; config.server.port",
+                "9000",
+            ),
+            ("let xs = [1, [2, 3]]; xs[1][0] = 5; xs", "[1, [5, 3]]"),
+            ("let o = { a: 1 }; fn set() { o.a = 7 }; set(); o.a", "7"),
+            ("let o = { a: 1 }; let p = o; p.a = 2; o.a", "1"),
+            ("let i = 0; let xs = [0, 0]; xs[i = 1] = i + 1; xs", "[0, 2]"),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(expected, run(src).unwrap().to_string(), "{src}");
+        }
+
+        let cases = [
+            (
+                "const o = { a: 1 }; o.a = 2",
+                "Cannot reassign to constant o",
+            ),
+            ("let o = {}; o.a.b = 1", "Object has no property a"),
+            ("let n = 1; n.a = 1", "Cannot access property a of integer"),
+            (
+                "let xs = []; xs[0] = 1",
+                "Index 0 is out of bounds for an array of length 0",
+            ),
+            (
+                "let o = {}; o.missing.a = 1",
+                "Object has no property missing",
+            ),
+            ("let a = {}; (a).b = 1; (1 + 2).b = 1", "Invalid assignment"),
+        ];
+
+        for (src, message) in cases {
+            let error = run(src).unwrap_err();
+            assert_eq!(message, error.to_string(), "{src}");
+        }
+    }
 }
@@ -1,110 +1,506 @@
-use crate::{error::LexerError, token::*};
+use std::{iter::Peekable, str::CharIndices};
 
-pub struct Lexer;
+use anyhow::anyhow;
 
-impl Lexer {
-    pub fn tokenize(src: impl Into<String>) -> Result<Vec<Token>, LexerError> {
+use crate::{
+    error::{LexerError, Result, WithSpan},
+    span::Span,
+    token::*,
+};
+
+pub struct Lexer<'a> {
+    src: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    line: usize,
+    column: usize,
+    source: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn tokenize(src: &str) -> Result<Vec<Token>> {
+        Self::tokenize_source(src, 0)
+    }
+
+    /// Tokenizes `src`, tagging every span with the `source` it belongs to.
+    pub fn tokenize_source(src: &str, source: usize) -> Result<Vec<Token>> {
+        let mut lexer = Lexer::new(src, source);
         let mut tokens = vec![];
-        let src: String = src.into();
-
-        let mut src = src.chars().peekable();
-
-        while let Some(c) = src.next() {
-            if Self::is_skippable(c) {
-                continue;
-            }
-
-            let token = match c {
-                '(' => Token::new(c.to_string(), TokenType::LeftParen),
-                ')' => Token::new(c.to_string(), TokenType::RightParen),
-                '{' => Token::new(c.to_string(), TokenType::LeftBrace),
-                '[' => Token::new(c.to_string(), TokenType::LeftBracket),
-                ']' => Token::new(c.to_string(), TokenType::RightBracket),
-                '}' => Token::new(c.to_string(), TokenType::RightBrace),
-                '/' => {
-                    if let Some(c2) = src.peek() {
-                        if *c2 != '/' && *c2 != '*' {
-                            Token::new(c.to_string(), TokenType::BinaryOperator)
-                        } else {
-                            let mut comment = String::new();
-                            if let Some(c2) = src.next() {
-                                match c2 {
-                                    '/' => {
-                                        // Single-line comment
-                                        for next_char in src.by_ref() {
-                                            if next_char == '\n' {
-                                                break;
-                                            }
-                                            comment.push(next_char);
-                                        }
-                                    }
-                                    '*' => {
-                                        // Multi-line comment
-                                        while let Some(next_char) = src.next() {
-                                            if next_char == '*' {
-                                                if let Some('/') = src.next() {
-                                                    break;
-                                                }
-                                                comment.push(next_char);
-                                            }
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                            Token::new(comment, TokenType::Comment)
+
+        while lexer.next_token(&mut tokens)? {}
+
+        tokens.push(Token::new("", TokenType::Eof, lexer.start_span()));
+        Ok(tokens)
+    }
+
+    fn new(src: &'a str, source: usize) -> Self {
+        Self {
+            src,
+            chars: src.char_indices().peekable(),
+            line: 1,
+            column: 1,
+            source,
+        }
+    }
+
+    /// Lexes the next token onto `tokens`, returns `false` once the source is exhausted.
+    fn next_token(&mut self, tokens: &mut Vec<Token>) -> Result<bool> {
+        self.skip_whitespace();
+
+        let mut span = self.start_span();
+        let Some(c) = self.bump() else {
+            return Ok(false);
+        };
+
+        let (value, token_type) = match c {
+            '(' => (c.to_string(), TokenType::LeftParen),
+            ')' => (c.to_string(), TokenType::RightParen),
+            '{' => (c.to_string(), TokenType::LeftBrace),
+            '[' => (c.to_string(), TokenType::LeftBracket),
+            ']' => (c.to_string(), TokenType::RightBracket),
+            '}' => (c.to_string(), TokenType::RightBrace),
+            '/' => match self.peek() {
+                Some('/') => {
+                    // Single-line comment
+                    self.bump();
+                    let mut comment = String::new();
+                    while let Some(next_char) = self.bump() {
+                        if next_char == '\n' {
+                            break;
                         }
-                    } else {
-                        Token::new(c.to_string(), TokenType::BinaryOperator)
+                        comment.push(next_char);
                     }
+                    (comment, TokenType::Comment)
                 }
-                '+' | '-' | '*' | '%' => Token::new(c.to_string(), TokenType::BinaryOperator),
-                '=' => Token::new(c.to_string(), TokenType::Equals),
-                '.' => Token::new(c.to_string(), TokenType::Dot),
-                ',' => Token::new(c.to_string(), TokenType::Comma),
-                ':' => Token::new(c.to_string(), TokenType::Colon),
-                ';' => Token::new(c.to_string(), TokenType::Semicolon),
-                _ => {
-                    if Self::is_numeric(c) {
-                        let mut num = String::new();
-                        num.push(c);
-
-                        while let Some(c) = src.peek() {
-                            if !Self::is_numeric(*c) {
-                                break;
-                            }
-                            num.push(src.next().unwrap());
+                Some('*') => {
+                    // Multi-line comment
+                    self.bump();
+                    let mut comment = String::new();
+                    while let Some(next_char) = self.bump() {
+                        if next_char == '*' && self.peek() == Some('/') {
+                            self.bump();
+                            break;
                         }
-                        Token::new(num, TokenType::Number)
-                    } else if Self::is_alpha(c) {
-                        let mut ident = String::new();
-                        ident.push(c);
+                        comment.push(next_char);
+                    }
+                    (comment, TokenType::Comment)
+                }
+                _ => self.lex_operator(c),
+            },
+            '+' | '-' | '*' | '%' => self.lex_operator(c),
+            '=' | '!' | '<' | '>' => self.lex_comparison(c),
+            '&' | '|' => self.lex_logical(c, span)?,
+            '~' => (c.to_string(), TokenType::UnaryOperator),
+            '@' => (c.to_string(), TokenType::At),
+            '.' => self.lex_dot(),
+            ',' => (c.to_string(), TokenType::Comma),
+            ':' => (c.to_string(), TokenType::Colon),
+            ';' => (c.to_string(), TokenType::Semicolon),
+            '"' => {
+                self.lex_string(span, tokens)?;
+                return Ok(true);
+            }
+            _ => {
+                if Self::is_numeric(c) {
+                    self.lex_number(c, span)?
+                } else if Self::is_alpha(c) {
+                    let mut ident = String::new();
+                    ident.push(c);
 
-                        while let Some(c) = src.peek() {
-                            if !Self::is_ident_compliant(*c) {
-                                break;
-                            }
-                            ident.push(src.next().unwrap());
+                    while let Some(c) = self.peek() {
+                        if !Self::is_ident_compliant(c) {
+                            break;
                         }
+                        ident.push(c);
+                        self.bump();
+                    }
 
-                        if let Some(reserved_token) = TokenType::from_keyword(&ident) {
-                            Token::new(ident, reserved_token)
-                        } else {
-                            Token::new(ident, TokenType::Identifier)
-                        }
-                    } else if Self::is_skippable(c) {
-                        continue;
+                    if let Some(reserved_token) = TokenType::from_keyword(&ident) {
+                        (ident, reserved_token)
                     } else {
-                        return Err(LexerError::UnexpectedCharacter(c));
+                        (ident, TokenType::Identifier)
                     }
+                } else {
+                    span.end = self.offset();
+                    return Err(anyhow!(LexerError::UnexpectedCharacter(c))).with_span(span);
                 }
-            };
+            }
+        };
+
+        span.end = self.offset();
+        tokens.push(Token::new(value, token_type, span));
+        Ok(true)
+    }
 
-            tokens.push(token);
+    /// + - * / %, or += -= *= /= %=
+    fn lex_operator(&mut self, first: char) -> (String, TokenType) {
+        if self.peek() == Some('=') {
+            self.bump();
+            return (format!("{first}="), TokenType::CompoundAssignment);
         }
 
-        tokens.push(Token::new("", TokenType::Eof));
-        Ok(tokens)
+        (first.to_string(), TokenType::BinaryOperator)
+    }
+
+    /// == != < <= > >=, or a lone = or !
+    fn lex_comparison(&mut self, first: char) -> (String, TokenType) {
+        if self.peek() == Some('=') {
+            self.bump();
+            return (format!("{first}="), TokenType::ComparisonOperator);
+        }
+
+        let token_type = match first {
+            '=' => TokenType::Equals,
+            '!' => TokenType::UnaryOperator,
+            _ => TokenType::ComparisonOperator,
+        };
+        (first.to_string(), token_type)
+    }
+
+    /// . .. ..= ...
+    fn lex_dot(&mut self) -> (String, TokenType) {
+        if self.peek() != Some('.') {
+            return (".".to_owned(), TokenType::Dot);
+        }
+
+        self.bump();
+        match self.peek() {
+            Some('=') => {
+                self.bump();
+                return ("..=".to_owned(), TokenType::RangeOperator);
+            }
+            Some('.') => {
+                self.bump();
+                return ("...".to_owned(), TokenType::Ellipsis);
+            }
+            _ => {}
+        }
+        ("..".to_owned(), TokenType::RangeOperator)
+    }
+
+    /// && ||
+    fn lex_logical(&mut self, first: char, mut span: Span) -> Result<(String, TokenType)> {
+        if self.peek() != Some(first) {
+            span.end = self.offset();
+            return Err(anyhow!(LexerError::UnexpectedCharacter(first))).with_span(span);
+        }
+
+        self.bump();
+        Ok((format!("{first}{first}"), TokenType::LogicalOperator))
+    }
+
+    /// 42, 1_000, 1.5, 1e9, 2.5E-3, 0xFF, 0o755, 0b1010
+    ///
+    /// Underscores may separate digits and are stripped from the token. Radix
+    /// literals keep their prefix so the parser knows how to read them.
+    fn lex_number(&mut self, first: char, start: Span) -> Result<(String, TokenType)> {
+        if first == '0' {
+            if let Some(prefix @ ('x' | 'o' | 'b')) = self.peek() {
+                self.bump();
+                return self.lex_radix_number(prefix, start);
+            }
+        }
+
+        let mut num = String::new();
+        let mut token_type = TokenType::Integer;
+        num.push(first);
+        self.lex_digits(&mut num);
+
+        if self.peek() == Some('.') && self.peek_nth(1).is_some_and(Self::is_numeric) {
+            token_type = TokenType::Float;
+            num.push('.');
+            self.bump();
+            self.lex_digits(&mut num);
+        }
+
+        if let Some(e @ ('e' | 'E')) = self.peek() {
+            let signed = matches!(self.peek_nth(1), Some('+' | '-'));
+            let digit = self.peek_nth(if signed { 2 } else { 1 });
+
+            if digit.is_some_and(Self::is_numeric) {
+                token_type = TokenType::Float;
+                num.push(e);
+                self.bump();
+                if signed {
+                    num.push(self.bump().unwrap());
+                }
+                self.lex_digits(&mut num);
+            }
+        }
+
+        let digits = self.strip_separators(&num, &num, 10, start)?;
+        Ok((digits, token_type))
+    }
+
+    /// 0xFF, 0o755, 0b1010, the leading `0` and radix prefix are already consumed.
+    fn lex_radix_number(&mut self, prefix: char, start: Span) -> Result<(String, TokenType)> {
+        let (radix, name) = match prefix {
+            'x' => (16, "hexadecimal"),
+            'o' => (8, "octal"),
+            _ => (2, "binary"),
+        };
+
+        // Take in every alphanumeric character so `0b102` is rejected as a
+        // whole instead of being split into `0b10` and `2`.
+        let mut digits = String::new();
+        while let Some(c) = self.peek() {
+            if !c.is_alphanumeric() && c != '_' {
+                break;
+            }
+            digits.push(c);
+            self.bump();
+        }
+
+        let literal = format!("0{prefix}{digits}");
+        let malformed = |lexer: &mut Self, reason: String| {
+            Err(anyhow!(LexerError::MalformedNumber(
+                literal.clone(),
+                reason
+            )))
+            .with_span(start.to(lexer.start_span()))
+        };
+
+        if !digits.chars().any(|c| c != '_') {
+            return malformed(self, format!("expected {name} digits after 0{prefix}"));
+        }
+
+        if let Some(c) = digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+            return malformed(self, format!("`{c}` is not a valid {name} digit"));
+        }
+
+        let digits = self.strip_separators(&literal, &digits, radix, start)?;
+        Ok((format!("0{prefix}{digits}"), TokenType::Integer))
+    }
+
+    /// Digits and `_` separators.
+    fn lex_digits(&mut self, num: &mut String) {
+        while let Some(c) = self.peek() {
+            if !Self::is_numeric(c) && c != '_' {
+                break;
+            }
+            num.push(c);
+            self.bump();
+        }
+    }
+
+    /// Removes `_` separators from `digits`, which are only allowed between two digits.
+    fn strip_separators(
+        &mut self,
+        literal: &str,
+        digits: &str,
+        radix: u32,
+        start: Span,
+    ) -> Result<String> {
+        let chars: Vec<char> = digits.chars().collect();
+        let is_digit = |i: Option<usize>| {
+            i.and_then(|i| chars.get(i))
+                .is_some_and(|c| c.is_digit(radix))
+        };
+
+        for (i, c) in chars.iter().enumerate() {
+            if *c == '_' && !(is_digit(i.checked_sub(1)) && is_digit(Some(i + 1))) {
+                return Err(anyhow!(LexerError::MalformedNumber(
+                    literal.to_owned(),
+                    "`_` may only appear between two digits".to_owned()
+                )))
+                .with_span(start.to(self.start_span()));
+            }
+        }
+
+        Ok(digits.replace('_', ""))
+    }
+
+    /// "foo\tbar"
+    /// ^.......^
+    ///
+    /// A string without interpolations becomes a single `String` token. Otherwise the
+    /// literal is split up into its fragments and the tokens of each interpolation:
+    ///
+    /// "a {b} c" -> TemplateStart String(a ) InterpolationStart Identifier(b)
+    ///              InterpolationEnd String( c) TemplateEnd
+    fn lex_string(&mut self, start: Span, tokens: &mut Vec<Token>) -> Result<()> {
+        let mut parts = vec![];
+        let mut fragment = String::new();
+        let mut fragment_span = self.start_span();
+
+        let end = loop {
+            let mut span = self.start_span();
+            match self.bump() {
+                Some('"') => {
+                    span.end = self.offset();
+                    break span;
+                }
+                Some('\\') => fragment.push(self.lex_escape(span)?),
+                Some('{') => {
+                    if !fragment.is_empty() {
+                        fragment_span.end = span.start;
+                        parts.push(Token::new(
+                            std::mem::take(&mut fragment),
+                            TokenType::String,
+                            fragment_span,
+                        ));
+                    }
+
+                    span.end = self.offset();
+                    parts.push(Token::new("{", TokenType::InterpolationStart, span));
+                    self.lex_interpolation(start, &mut parts)?;
+                    fragment_span = self.start_span();
+                }
+                Some(c) => fragment.push(c),
+                None => {
+                    return Err(anyhow!(LexerError::UnterminatedString))
+                        .with_span(start.to(self.start_span()));
+                }
+            }
+        };
+
+        if parts.is_empty() {
+            tokens.push(Token::new(fragment, TokenType::String, start.to(end)));
+            return Ok(());
+        }
+
+        if !fragment.is_empty() {
+            fragment_span.end = end.start;
+            parts.push(Token::new(fragment, TokenType::String, fragment_span));
+        }
+
+        let quote = Span {
+            end: start.start + 1,
+            ..start
+        };
+        tokens.push(Token::new("\"", TokenType::TemplateStart, quote));
+        tokens.append(&mut parts);
+        tokens.push(Token::new("\"", TokenType::TemplateEnd, end));
+        Ok(())
+    }
+
+    /// "a {b + c} d"
+    ///     ^....^
+    ///
+    /// Lexes the tokens of an interpolation up to and including its closing brace.
+    fn lex_interpolation(&mut self, string_start: Span, tokens: &mut Vec<Token>) -> Result<()> {
+        let mut depth = 0;
+
+        loop {
+            self.skip_whitespace();
+
+            if depth == 0 && self.peek() == Some('}') {
+                let mut span = self.start_span();
+                self.bump();
+                span.end = self.offset();
+                tokens.push(Token::new("}", TokenType::InterpolationEnd, span));
+                return Ok(());
+            }
+
+            if !self.next_token(tokens)? {
+                return Err(anyhow!(LexerError::UnterminatedString))
+                    .with_span(string_start.to(self.start_span()));
+            }
+
+            match tokens.last().map(|t| t.token_type) {
+                Some(TokenType::LeftBrace) => depth += 1,
+                Some(TokenType::RightBrace) => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// \n, \t, \", \\, \{, \} or \u{...}, the leading backslash is already consumed.
+    fn lex_escape(&mut self, start: Span) -> Result<char> {
+        let c = match self.bump() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('{') => '{',
+            Some('}') => '}',
+            Some('u') => return self.lex_unicode_escape(start),
+            Some(c) => {
+                return Err(anyhow!(LexerError::InvalidEscape(c)))
+                    .with_span(start.to(self.start_span()));
+            }
+            None => {
+                return Err(anyhow!(LexerError::UnterminatedString))
+                    .with_span(start.to(self.start_span()));
+            }
+        };
+
+        Ok(c)
+    }
+
+    fn lex_unicode_escape(&mut self, start: Span) -> Result<char> {
+        let mut digits = String::new();
+        let mut terminated = false;
+
+        if self.peek() == Some('{') {
+            self.bump();
+            while let Some(c) = self.peek() {
+                if c == '}' {
+                    self.bump();
+                    terminated = true;
+                    break;
+                }
+                if !c.is_ascii_hexdigit() || digits.len() == 6 {
+                    break;
+                }
+                digits.push(c);
+                self.bump();
+            }
+        }
+
+        let c = u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|_| terminated)
+            .and_then(char::from_u32);
+
+        match c {
+            Some(c) => Ok(c),
+            None => Err(anyhow!(LexerError::InvalidUnicodeEscape(digits)))
+                .with_span(start.to(self.start_span())),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !Self::is_skippable(c) {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    /// Looks `n` characters past the next one without consuming anything.
+    fn peek_nth(&mut self, n: usize) -> Option<char> {
+        let offset = self.offset();
+        self.src[offset..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Byte offset of the next character to be consumed.
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.src.len(), |(i, _)| *i)
+    }
+
+    /// An empty span positioned at the next character to be consumed.
+    fn start_span(&mut self) -> Span {
+        let offset = self.offset();
+        Span {
+            source: self.source,
+            ..Span::new(offset, offset, self.line, self.column)
+        }
     }
 
     fn is_ident_compliant(c: char) -> bool {
@@ -116,7 +512,7 @@ impl Lexer {
     }
 
     fn is_numeric(c: char) -> bool {
-        c.is_numeric()
+        c.is_ascii_digit()
     }
 
     fn is_alpha_numeric(c: char) -> bool {
@@ -131,15 +527,11 @@ impl Lexer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::SpannedError;
 
     fn assert(token: &Token, value: &str, token_type: TokenType) {
-        assert_eq!(
-            Token {
-                value: value.to_string(),
-                token_type
-            },
-            *token
-        );
+        assert_eq!(value, token.value);
+        assert_eq!(token_type, token.token_type);
     }
 
     #[test]
@@ -151,14 +543,293 @@ mod tests {
         assert(&tokens[0], "let", TokenType::LetKeyword);
         assert(&tokens[1], "x", TokenType::Identifier);
         assert(&tokens[2], "=", TokenType::Equals);
-        assert(&tokens[3], "5", TokenType::Number);
+        assert(&tokens[3], "5", TokenType::Integer);
         assert(&tokens[4], "+", TokenType::BinaryOperator);
         assert(&tokens[5], "(", TokenType::LeftParen);
-        assert(&tokens[6], "4", TokenType::Number);
+        assert(&tokens[6], "4", TokenType::Integer);
         assert(&tokens[7], "/", TokenType::BinaryOperator);
-        assert(&tokens[8], "3", TokenType::Number);
+        assert(&tokens[8], "3", TokenType::Integer);
         assert(&tokens[9], ")", TokenType::RightParen);
         assert(&tokens[10], ";", TokenType::Semicolon);
         assert(&tokens[11], "", TokenType::Eof);
     }
+
+    #[test]
+    fn numbers() {
+        let src = "1 1.5 1e9 2.5E-3 7e+2 3.foo 4e 0..10 123456789012345678901234567890";
+
+        let tokens = Lexer::tokenize(src).expect("Couldnt tokenize input");
+
+        let expected = [
+            ("1", TokenType::Integer),
+            ("1.5", TokenType::Float),
+            ("1e9", TokenType::Float),
+            ("2.5E-3", TokenType::Float),
+            ("7e+2", TokenType::Float),
+            ("3", TokenType::Integer),
+            (".", TokenType::Dot),
+            ("foo", TokenType::Identifier),
+            ("4", TokenType::Integer),
+            ("e", TokenType::Identifier),
+            ("0", TokenType::Integer),
+            ("..", TokenType::RangeOperator),
+            ("10", TokenType::Integer),
+            ("123456789012345678901234567890", TokenType::Integer),
+            ("", TokenType::Eof),
+        ];
+
+        assert_eq!(expected.len(), tokens.len());
+        for (token, (value, token_type)) in tokens.iter().zip(expected) {
+            assert(token, value, token_type);
+        }
+    }
+
+    #[test]
+    fn radix_numbers_and_separators() {
+        let src = "0xFF 0o755 0b1010_0101 1_000_000 1_000.000_1 0";
+
+        let tokens = Lexer::tokenize(src).expect("Couldnt tokenize input");
+
+        assert(&tokens[0], "0xFF", TokenType::Integer);
+        assert(&tokens[1], "0o755", TokenType::Integer);
+        assert(&tokens[2], "0b10100101", TokenType::Integer);
+        assert(&tokens[3], "1000000", TokenType::Integer);
+        assert_eq!(Span::new(23, 32, 1, 24), tokens[3].span);
+        assert(&tokens[4], "1000.0001", TokenType::Float);
+        assert(&tokens[5], "0", TokenType::Integer);
+    }
+
+    #[test]
+    fn malformed_numbers() {
+        let cases = [
+            (
+                "0x",
+                "Malformed numeric literal 0x, expected hexadecimal digits after 0x",
+            ),
+            (
+                "0x_ff",
+                "Malformed numeric literal 0x_ff, `_` may only appear between two digits",
+            ),
+            (
+                "0b102",
+                "Malformed numeric literal 0b102, `2` is not a valid binary digit",
+            ),
+            (
+                "0o8",
+                "Malformed numeric literal 0o8, `8` is not a valid octal digit",
+            ),
+            (
+                "0xFG",
+                "Malformed numeric literal 0xFG, `G` is not a valid hexadecimal digit",
+            ),
+            (
+                "1_",
+                "Malformed numeric literal 1_, `_` may only appear between two digits",
+            ),
+            (
+                "1__0",
+                "Malformed numeric literal 1__0, `_` may only appear between two digits",
+            ),
+            (
+                "1_.5",
+                "Malformed numeric literal 1_.5, `_` may only appear between two digits",
+            ),
+        ];
+
+        for (src, message) in cases {
+            let error = Lexer::tokenize(src).expect_err(src);
+            let error = error.downcast_ref::<SpannedError>().unwrap();
+            assert_eq!(message, error.to_string());
+            assert_eq!(Span::new(0, src.len(), 1, 1), error.span, "{src}");
+        }
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let src = "a += b -= c *= d /= e %= f / g";
+
+        let tokens = Lexer::tokenize(src).expect("Couldnt tokenize input");
+
+        let operators: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.token_type != TokenType::Identifier && t.token_type != TokenType::Eof)
+            .map(|t| (t.value.as_str(), t.token_type))
+            .collect();
+        assert_eq!(
+            vec![
+                ("+=", TokenType::CompoundAssignment),
+                ("-=", TokenType::CompoundAssignment),
+                ("*=", TokenType::CompoundAssignment),
+                ("/=", TokenType::CompoundAssignment),
+                ("%=", TokenType::CompoundAssignment),
+                ("/", TokenType::BinaryOperator),
+            ],
+            operators
+        );
+    }
+
+    #[test]
+    fn comparison_operators() {
+        let src = "a == b != c < d <= e > f >= g = h";
+
+        let tokens = Lexer::tokenize(src).expect("Couldnt tokenize input");
+
+        let operators: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.token_type != TokenType::Identifier && t.token_type != TokenType::Eof)
+            .map(|t| (t.value.as_str(), t.token_type))
+            .collect();
+        assert_eq!(
+            vec![
+                ("==", TokenType::ComparisonOperator),
+                ("!=", TokenType::ComparisonOperator),
+                ("<", TokenType::ComparisonOperator),
+                ("<=", TokenType::ComparisonOperator),
+                (">", TokenType::ComparisonOperator),
+                (">=", TokenType::ComparisonOperator),
+                ("=", TokenType::Equals),
+            ],
+            operators
+        );
+        assert_eq!(Span::new(2, 4, 1, 3), tokens[1].span);
+    }
+
+    #[test]
+    fn logical_operators() {
+        let src = "!a && b || !c";
+
+        let tokens = Lexer::tokenize(src).expect("Couldnt tokenize input");
+
+        assert(&tokens[0], "!", TokenType::UnaryOperator);
+        assert(&tokens[2], "&&", TokenType::LogicalOperator);
+        assert(&tokens[4], "||", TokenType::LogicalOperator);
+        assert(&tokens[5], "!", TokenType::UnaryOperator);
+        assert_eq!(Span::new(3, 5, 1, 4), tokens[2].span);
+
+        let error = Lexer::tokenize("a & b").expect_err("lone &");
+        assert_eq!("Unexpected character &", error.to_string());
+    }
+
+    #[test]
+    fn ranges() {
+        let src = "a.b 0..10 1..=n ...xs";
+
+        let tokens = Lexer::tokenize(src).expect("Couldnt tokenize input");
+
+        assert(&tokens[1], ".", TokenType::Dot);
+        assert(&tokens[4], "..", TokenType::RangeOperator);
+        assert(&tokens[7], "..=", TokenType::RangeOperator);
+        assert_eq!(Span::new(11, 14, 1, 12), tokens[7].span);
+        assert(&tokens[9], "...", TokenType::Ellipsis);
+    }
+
+    #[test]
+    fn reference() {
+        let src = "@y += 4";
+
+        let tokens = Lexer::tokenize(src).expect("Couldnt tokenize input");
+
+        assert(&tokens[0], "@", TokenType::At);
+        assert(&tokens[1], "y", TokenType::Identifier);
+        assert(&tokens[2], "+=", TokenType::CompoundAssignment);
+    }
+
+    #[test]
+    fn string() {
+        let src = r#"print("a \"quoted\"\tword\n", "\\\u{1F600}", "");"#;
+
+        let tokens = Lexer::tokenize(src).expect("Couldnt tokenize input");
+
+        assert(&tokens[2], "a \"quoted\"\tword\n", TokenType::String);
+        assert(&tokens[4], "\\\u{1F600}", TokenType::String);
+        assert(&tokens[6], "", TokenType::String);
+        assert_eq!(Span::new(6, 28, 1, 7), tokens[2].span);
+    }
+
+    #[test]
+    fn interpolated_string() {
+        let src = r#""a {b + "{c}"} \{d} {{ e: 1 }}""#;
+
+        let tokens = Lexer::tokenize(src).expect("Couldnt tokenize input");
+
+        let expected = [
+            ("\"", TokenType::TemplateStart),
+            ("a ", TokenType::String),
+            ("{", TokenType::InterpolationStart),
+            ("b", TokenType::Identifier),
+            ("+", TokenType::BinaryOperator),
+            ("\"", TokenType::TemplateStart),
+            ("{", TokenType::InterpolationStart),
+            ("c", TokenType::Identifier),
+            ("}", TokenType::InterpolationEnd),
+            ("\"", TokenType::TemplateEnd),
+            ("}", TokenType::InterpolationEnd),
+            (" {d} ", TokenType::String),
+            ("{", TokenType::InterpolationStart),
+            ("{", TokenType::LeftBrace),
+            ("e", TokenType::Identifier),
+            (":", TokenType::Colon),
+            ("1", TokenType::Integer),
+            ("}", TokenType::RightBrace),
+            ("}", TokenType::InterpolationEnd),
+            ("\"", TokenType::TemplateEnd),
+            ("", TokenType::Eof),
+        ];
+
+        assert_eq!(expected.len(), tokens.len());
+        for (token, (value, token_type)) in tokens.iter().zip(expected) {
+            assert(token, value, token_type);
+        }
+        assert_eq!(Span::new(1, 3, 1, 2), tokens[1].span);
+        assert_eq!(Span::new(14, 20, 1, 15), tokens[11].span);
+    }
+
+    #[test]
+    fn invalid_string() {
+        let cases = [
+            (
+                r#""foo"#,
+                "Unterminated string literal",
+                Span::new(0, 4, 1, 1),
+            ),
+            (
+                r#""a\qb""#,
+                "Unknown escape sequence \\q",
+                Span::new(2, 4, 1, 3),
+            ),
+            (
+                r#""\u{110000}""#,
+                "Invalid unicode escape \\u{110000}",
+                Span::new(1, 11, 1, 2),
+            ),
+            (
+                r#""\u{12""#,
+                "Invalid unicode escape \\u{12}",
+                Span::new(1, 6, 1, 2),
+            ),
+        ];
+
+        for (src, message, span) in cases {
+            let error = Lexer::tokenize(src).expect_err("Should not tokenize");
+            let error = error.downcast_ref::<SpannedError>().unwrap();
+            assert_eq!(message, error.to_string());
+            assert_eq!(span, error.span);
+        }
+    }
+
+    #[test]
+    fn spans() {
+        let src = "let foo = 5;\n  bar(ü, 12)";
+
+        let tokens = Lexer::tokenize(src).expect("Couldnt tokenize input");
+
+        assert_eq!(Span::new(0, 3, 1, 1), tokens[0].span);
+        assert_eq!(Span::new(4, 7, 1, 5), tokens[1].span);
+        assert_eq!(Span::new(10, 11, 1, 11), tokens[3].span);
+        assert_eq!(Span::new(15, 18, 2, 3), tokens[5].span);
+        // `ü` is two bytes wide but a single column
+        assert_eq!(Span::new(19, 21, 2, 7), tokens[7].span);
+        assert_eq!(Span::new(23, 25, 2, 10), tokens[9].span);
+        assert_eq!(Span::new(26, 26, 2, 13), tokens[11].span);
+    }
 }
@@ -1,45 +1,114 @@
 #![allow(unused)] // temp for development
 
 use std::{
+    fs,
     io::{stdin, stdout, Write},
     process::exit,
 };
 
+use diagnostic::Diagnostic;
 use environment::Environment;
 use parser::Parser;
+use values::RuntimeValue;
 
-use crate::interpreter::evaluate;
+use crate::interpreter::{evaluate, ArithmeticMode, STACK_SIZE};
 mod ast;
+mod bigint;
+mod diagnostic;
 mod environment;
 mod error;
 mod interpreter;
 mod lexer;
 mod parser;
+mod span;
 mod token;
 mod values;
 
 fn main() {
-    repl();
+    // Evaluation recurses for every nested call
+    let interpreter = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(start)
+        .expect("Unable to start the interpreter thread");
+
+    if interpreter.join().is_err() {
+        exit(1);
+    }
 }
 
-fn repl() {
+fn start() {
+    let mut path = None;
     let mut env = Environment::new();
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--checked" => env.set_arithmetic_mode(ArithmeticMode::Checked),
+            "--wrapping" => env.set_arithmetic_mode(ArithmeticMode::Wrapping),
+            "--saturating" => env.set_arithmetic_mode(ArithmeticMode::Saturating),
+            _ => path = Some(arg),
+        }
+    }
+
+    match path {
+        Some(path) => run_file(&path, env),
+        None => repl(env),
+    }
+}
+
+fn repl(mut env: Environment) {
     let mut parser = Parser::new();
+    // Every input is kept, functions declared earlier report errors in them
+    let mut history: Vec<String> = vec![];
 
     loop {
         let input = prompt();
 
-        if input.is_empty() || input == "exit" {
+        if input.is_empty() || input.trim() == "exit" {
+            exit(1);
+        }
+
+        history.push(input);
+        let source = history.len() - 1;
+
+        match run(&history[source], source, &mut parser, &mut env) {
+            Ok(RuntimeValue::Null) => {}
+            Ok(value) => println!("{value}"),
+            Err(e) => {
+                let diagnostic = Diagnostic::from_error(&e);
+                let input = diagnostic.span.map_or(source, |span| span.source);
+                eprint!("{}", diagnostic.render(&history[input], "<repl>"));
+            }
+        }
+    }
+}
+
+fn run_file(path: &str, mut env: Environment) {
+    let src = match fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) => {
+            eprintln!("Unable to read {path}: {e}");
             exit(1);
         }
+    };
 
-        let program = parser.produce_ast(input).expect("Unable to parse");
-        println!("{:?}", program);
+    let mut parser = Parser::new();
 
-        let result = evaluate(ast::Stmt::Program(program), &mut env);
+    if let Err(e) = run(&src, 0, &mut parser, &mut env) {
+        eprint!("{}", Diagnostic::from_error(&e).render(&src, path));
+        exit(1);
     }
 }
 
+fn run(
+    src: &str,
+    source: usize,
+    parser: &mut Parser,
+    env: &mut Environment,
+) -> error::Result<RuntimeValue> {
+    let program = parser.produce_ast_from(src.to_owned(), source)?;
+    evaluate(ast::StmtKind::Program(program).into(), env)
+}
+
 fn prompt() -> String {
     let mut input = String::new();
     let stdin = stdin();
@@ -4,25 +4,45 @@ use std::vec::IntoIter;
 use anyhow::anyhow;
 
 use crate::ast::*;
+use crate::bigint::BigInt;
 use crate::error::ParseError;
 use crate::error::Result;
+use crate::error::WithSpan;
 use crate::lexer::*;
+use crate::span::Span;
 use crate::token::*;
 
 #[derive(Debug)]
 pub struct Parser {
     tokens: Peekable<IntoIter<Token>>,
+    previous: Span,
+    /// Number of function bodies being parsed, `return` is only valid inside one.
+    function_depth: usize,
 }
 
 impl Parser {
     pub fn new() -> Self {
         Self {
             tokens: vec![].into_iter().peekable(),
+            previous: Span::default(),
+            function_depth: 0,
         }
     }
 
     pub fn produce_ast(&mut self, src: String) -> Result<Program> {
-        self.tokens = Lexer::tokenize(src)?.into_iter().peekable();
+        self.produce_ast_from(src, 0)
+    }
+
+    /// Parses `src` as the input numbered `source`, see `Span::source`.
+    pub fn produce_ast_from(&mut self, src: String, source: usize) -> Result<Program> {
+        // Comments carry no meaning, so the parser never sees them
+        let tokens: Vec<Token> = Lexer::tokenize_source(&src, source)?
+            .into_iter()
+            .filter(|t| t.token_type != TokenType::Comment)
+            .collect();
+        self.tokens = tokens.into_iter().peekable();
+        self.previous = Span::default();
+        self.function_depth = 0;
 
         let mut program = Program { body: vec![] };
 
@@ -40,28 +60,47 @@ impl Parser {
     fn parse_stmt(&mut self) -> Result<Stmt> {
         if let Some(t) = self.peek() {
             let stmt = match t.token_type {
-                TokenType::Comment => self.parse_comment_declaration(),
                 TokenType::LetKeyword | TokenType::ConstKeyword => {
                     self.parse_variable_declaration()
                 }
                 TokenType::FnKeyword => self.parse_function_declaration(),
+                TokenType::BreakKeyword | TokenType::ReturnKeyword => self.parse_jump_stmt(),
+                // A block in statement position ends the statement, so that
+                // `if a { b } -c` is not read as a subtraction
+                TokenType::IfKeyword
+                | TokenType::WhileKeyword
+                | TokenType::LoopKeyword
+                | TokenType::ForKeyword => self.parse_primary_expr(),
+                TokenType::ContinueKeyword => {
+                    let start = self.start();
+                    self.eat();
+                    Ok(self.node(StmtKind::Continue, start))
+                }
                 _ => self.parse_expr(),
-            };
-            self.expect(TokenType::Semicolon, "Expected semicolon after statement");
-            return stmt;
+            }?;
+            self.parse_terminator(&stmt)?;
+            return Ok(stmt);
         }
-        Err(anyhow!(ParseError::ExpectedToken))
+        Err(anyhow!(ParseError::ExpectedToken)).with_span(self.previous)
     }
 
-    fn parse_comment_declaration(&mut self) -> Result<Stmt> {
+    /// Statements are separated by semicolons, which may be left out after a
+    /// statement ending in a block and after the last statement of a block.
+    fn parse_terminator(&mut self, stmt: &Stmt) -> Result<()> {
         if let Some(t) = self.peek() {
-            if let TokenType::Comment = t.token_type {
-                let comment = Stmt::Comment(t.value.to_owned());
-                return Ok(comment);
+            match t.token_type {
+                TokenType::Semicolon => {
+                    self.eat();
+                }
+                TokenType::RightBrace | TokenType::Eof => {}
+                _ if stmt.kind.ends_with_block() => {}
+                _ => {
+                    self.expect(TokenType::Semicolon, "Expected semicolon after statement")?;
+                }
             }
         }
 
-        Err(anyhow!(ParseError::ExpectedToken))
+        Ok(())
     }
 
     fn parse_expr(&mut self) -> Result<Stmt> {
@@ -69,15 +108,140 @@ impl Parser {
     }
 
     fn parse_assignment_expr(&mut self) -> Result<Stmt> {
-        let left = self.parse_object_expr()?;
+        let start = self.start();
+        let left = if self.at_reference() {
+            self.parse_reference(&[TokenType::Equals, TokenType::CompoundAssignment])?
+        } else {
+            self.parse_object_expr()?
+        };
         if let Some(t) = self.peek() {
             if t.token_type == TokenType::Equals {
                 self.eat();
                 let value = self.parse_assignment_expr()?;
-                return Ok(Stmt::AssignmentExpr {
-                    assignee: Box::new(left),
-                    value: Box::new(value),
-                });
+                return Ok(self.node(
+                    StmtKind::AssignmentExpr {
+                        assignee: Box::new(left),
+                        value: Box::new(value),
+                    },
+                    start,
+                ));
+            }
+
+            if t.token_type == TokenType::CompoundAssignment {
+                let operator = self.eat().unwrap().value.trim_end_matches('=').to_owned();
+                let value = self.parse_assignment_expr()?;
+                return Ok(self.node(
+                    StmtKind::CompoundAssignment {
+                        assignee: Box::new(left),
+                        value: Box::new(value),
+                        operator,
+                    },
+                    start,
+                ));
+            }
+        }
+
+        Ok(left)
+    }
+
+    /// a..b, a..=b
+    fn parse_range_expr(&mut self) -> Result<Stmt> {
+        let start = self.start();
+        let left = self.parse_logical_or_expr()?;
+
+        if let Some(t) = self.peek() {
+            if t.token_type == TokenType::RangeOperator {
+                let inclusive = self.eat().unwrap().value == "..=";
+                let right = self.parse_logical_or_expr()?;
+                return Ok(self.node(
+                    StmtKind::RangeExpr {
+                        start: Box::new(left),
+                        end: Box::new(right),
+                        inclusive,
+                    },
+                    start,
+                ));
+            }
+        }
+
+        Ok(left)
+    }
+
+    /// a || b
+    fn parse_logical_or_expr(&mut self) -> Result<Stmt> {
+        let start = self.start();
+        let mut left = self.parse_logical_and_expr()?;
+
+        while let Some(t) = self.peek() {
+            if t.value != "||" {
+                break;
+            }
+
+            if let Some(tr) = self.eat() {
+                let operator = tr.value;
+                let right = self.parse_logical_and_expr()?;
+                left = self.node(
+                    StmtKind::LogicalExpr {
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        operator,
+                    },
+                    start,
+                );
+            }
+        }
+
+        Ok(left)
+    }
+
+    /// a && b
+    fn parse_logical_and_expr(&mut self) -> Result<Stmt> {
+        let start = self.start();
+        let mut left = self.parse_comparison_expr()?;
+
+        while let Some(t) = self.peek() {
+            if t.value != "&&" {
+                break;
+            }
+
+            if let Some(tr) = self.eat() {
+                let operator = tr.value;
+                let right = self.parse_comparison_expr()?;
+                left = self.node(
+                    StmtKind::LogicalExpr {
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        operator,
+                    },
+                    start,
+                );
+            }
+        }
+
+        Ok(left)
+    }
+
+    /// a == b, a != b, a < b, a <= b, a > b, a >= b
+    fn parse_comparison_expr(&mut self) -> Result<Stmt> {
+        let start = self.start();
+        let mut left = self.parse_additive_expr()?;
+
+        while let Some(t) = self.peek() {
+            if t.token_type != TokenType::ComparisonOperator {
+                break;
+            }
+
+            if let Some(tr) = self.eat() {
+                let operator = tr.value;
+                let right = self.parse_additive_expr()?;
+                left = self.node(
+                    StmtKind::BinaryExpr {
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        operator,
+                    },
+                    start,
+                );
             }
         }
 
@@ -85,6 +249,7 @@ impl Parser {
     }
 
     fn parse_additive_expr(&mut self) -> Result<Stmt> {
+        let start = self.start();
         let mut left = self.parse_multiplicative_expr()?;
 
         while let Some(t) = self.peek() {
@@ -95,11 +260,14 @@ impl Parser {
             if let Some(tr) = self.eat() {
                 let operator = tr.value;
                 let right = self.parse_multiplicative_expr()?;
-                left = Stmt::BinaryExpr {
-                    left: Box::new(left),
-                    right: Box::new(right),
-                    operator,
-                };
+                left = self.node(
+                    StmtKind::BinaryExpr {
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        operator,
+                    },
+                    start,
+                );
             }
         }
 
@@ -107,7 +275,8 @@ impl Parser {
     }
 
     fn parse_multiplicative_expr(&mut self) -> Result<Stmt> {
-        let mut left = self.parse_call_member_expr()?;
+        let start = self.start();
+        let mut left = self.parse_unary_expr()?;
 
         while let Some(t) = self.peek() {
             if t.value != "*" && t.value != "/" && t.value != "%" {
@@ -116,37 +285,132 @@ impl Parser {
 
             if let Some(tr) = self.eat() {
                 let operator = tr.value;
-                let right = self.parse_call_member_expr()?;
-                left = Stmt::BinaryExpr {
-                    left: Box::new(left),
-                    right: Box::new(right),
-                    operator,
-                };
+                let right = self.parse_unary_expr()?;
+                left = self.node(
+                    StmtKind::BinaryExpr {
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        operator,
+                    },
+                    start,
+                );
             }
         }
 
         Ok(left)
     }
 
+    /// !a, -a, ~a
+    fn parse_unary_expr(&mut self) -> Result<Stmt> {
+        let start = self.start();
+        if let Some(t) = self.peek() {
+            if t.token_type == TokenType::UnaryOperator || t.value == "-" {
+                let operator = self.eat().unwrap().value;
+                let operand = self.parse_unary_expr()?;
+                return Ok(self.node(
+                    StmtKind::UnaryExpr {
+                        operator,
+                        operand: Box::new(operand),
+                    },
+                    start,
+                ));
+            }
+        }
+
+        self.parse_call_member_expr()
+    }
+
     fn parse_primary_expr(&mut self) -> Result<Stmt> {
         if let Some(t) = self.eat() {
             match t.token_type {
-                TokenType::Identifier => Ok(Stmt::Identifier(t.value.to_owned())),
-                TokenType::Number => Ok(Stmt::NumericLiteral(t.value.to_owned())),
+                TokenType::Identifier => Ok(Stmt::new(StmtKind::Identifier(t.value), t.span)),
+                TokenType::At => {
+                    let name = self
+                        .expect(TokenType::Identifier, "Expected a variable name after @")?
+                        .value;
+                    Err(anyhow!(ParseError::MisplacedReference(name)))
+                        .with_span(t.span.to(self.previous))
+                }
+                TokenType::Integer => self.parse_integer_literal(t),
+                TokenType::Float => match t.value.parse() {
+                    Ok(v) => Ok(Stmt::new(StmtKind::FloatLiteral(v), t.span)),
+                    Err(_) => {
+                        Err(anyhow!(ParseError::InvalidNumericLiteral(t.value))).with_span(t.span)
+                    }
+                },
+                TokenType::String => Ok(Stmt::new(StmtKind::StringLiteral(t.value), t.span)),
+                TokenType::TemplateStart => self.parse_template_literal(t.span),
+                TokenType::IfKeyword => self.parse_if_expr(t.span),
+                TokenType::FnKeyword => self.parse_fn_expr(t.span),
+                TokenType::WhileKeyword => self.parse_while_expr(t.span),
+                TokenType::ForKeyword => self.parse_for_expr(t.span),
+                TokenType::LeftBracket => self.parse_array_literal(t.span),
+                TokenType::LoopKeyword => {
+                    let body = self.parse_block("Expected block after loop")?;
+                    Ok(self.node(StmtKind::Loop(body), t.span))
+                }
                 TokenType::LeftParen => {
                     let value = self.parse_expr()?;
-                    self.expect(TokenType::RightParen, "No right paren inside expression");
+                    self.expect(TokenType::RightParen, "No right paren inside expression")?;
 
                     Ok(value)
                 }
-                _ => Err(anyhow!(ParseError::UnsupportedTokenType(t.token_type))),
+                _ => Err(anyhow!(ParseError::UnsupportedTokenType(t.token_type))).with_span(t.span),
             }
         } else {
-            Err(anyhow!(ParseError::ExpectedToken))
+            Err(anyhow!(ParseError::ExpectedToken)).with_span(self.previous)
+        }
+    }
+
+    /// Integers too large for an `i64` become arbitrary precision literals.
+    fn parse_integer_literal(&mut self, t: Token) -> Result<Stmt> {
+        let (digits, radix) = match t.value.get(..2) {
+            Some("0x") => (&t.value[2..], 16),
+            Some("0o") => (&t.value[2..], 8),
+            Some("0b") => (&t.value[2..], 2),
+            _ => (&t.value[..], 10),
+        };
+
+        if let Ok(v) = i64::from_str_radix(digits, radix) {
+            return Ok(Stmt::new(StmtKind::IntegerLiteral(v), t.span));
+        }
+
+        match BigInt::from_str_radix(digits, radix) {
+            Some(v) => Ok(Stmt::new(StmtKind::BigIntLiteral(v), t.span)),
+            None => Err(anyhow!(ParseError::InvalidNumericLiteral(t.value))).with_span(t.span),
+        }
+    }
+
+    /// "hello {name}!"
+    ///  ^...........^
+    fn parse_template_literal(&mut self, start: Span) -> Result<Stmt> {
+        let mut parts = vec![];
+
+        while let Some(t) = self.eat() {
+            match t.token_type {
+                TokenType::TemplateEnd => {
+                    return Ok(self.node(StmtKind::TemplateLiteral(parts), start));
+                }
+                TokenType::String => parts.push(TemplatePart::Literal(t.value)),
+                TokenType::InterpolationStart => {
+                    parts.push(TemplatePart::Expr(self.parse_expr()?));
+                    self.expect(
+                        TokenType::InterpolationEnd,
+                        "Expected closing brace after interpolated expression",
+                    )?;
+                }
+                _ => {
+                    return Err(anyhow!(ParseError::UnsupportedTokenType(t.token_type)))
+                        .with_span(t.span);
+                }
+            }
         }
+
+        Err(anyhow!(ParseError::ExpectedToken)).with_span(self.previous)
     }
 
     fn parse_variable_declaration(&mut self) -> Result<Stmt> {
+        let start = self.start();
         if let Some(t) = self.eat() {
             let constant = t.token_type == TokenType::ConstKeyword;
             let identifier = self
@@ -157,34 +421,57 @@ impl Parser {
                 .value;
             if let Some(t) = self.peek() {
                 if t.token_type == TokenType::Semicolon {
-                    self.eat();
                     if constant {
-                        return Err(anyhow!(ParseError::ConstValueRequired));
+                        return Err(anyhow!(ParseError::ConstValueRequired))
+                            .with_span(start.to(t.span));
                     }
 
-                    return Ok(Stmt::VarDeclaration {
-                        constant,
-                        identifier,
-                        value: None,
-                    });
+                    return Ok(self.node(
+                        StmtKind::VarDeclaration {
+                            constant,
+                            identifier,
+                            value: None,
+                        },
+                        start,
+                    ));
                 }
 
-                self.expect(TokenType::Equals, "Expected equals token after identifier");
-                let declaration = Stmt::VarDeclaration {
-                    constant,
-                    identifier,
-                    value: Some(Box::new(self.parse_expr()?)),
+                self.expect(TokenType::Equals, "Expected equals token after identifier")?;
+                let value = if !constant && self.at_reference() {
+                    self.parse_reference(&[
+                        TokenType::Semicolon,
+                        TokenType::RightBrace,
+                        TokenType::Eof,
+                    ])?
+                } else {
+                    self.parse_expr()?
                 };
+                let value = Some(Box::new(value));
+                let declaration = self.node(
+                    StmtKind::VarDeclaration {
+                        constant,
+                        identifier,
+                        value,
+                    },
+                    start,
+                );
 
                 return Ok(declaration);
             }
         }
 
-        Err(anyhow!(ParseError::ExpectedToken))
+        Err(anyhow!(ParseError::ExpectedToken)).with_span(self.previous)
     }
 
     fn parse_function_declaration(&mut self) -> Result<Stmt> {
+        let start = self.start();
         self.eat();
+
+        // An anonymous function used as a statement, `fn(x) { ... }`
+        if let Some(TokenType::LeftParen) = self.peek().map(|t| t.token_type) {
+            return self.parse_fn_expr(start);
+        }
+
         let name = self
             .expect(
                 TokenType::Identifier,
@@ -192,21 +479,215 @@ impl Parser {
             )?
             .value;
 
-        let args = self.parse_args()?;
+        let parameters = self.parse_parameters()?;
+        let body = self.parse_function_body("Expected function body following declaration")?;
+
+        let function = self.node(
+            StmtKind::FnDeclaration {
+                name,
+                parameters: parameters.into(),
+                body: body.into(),
+                is_const: false,
+            },
+            start,
+        );
+        Ok(function)
+    }
+
+    /// fn(a, b) { ... }
+    ///   ^ the `fn` keyword is already consumed
+    fn parse_fn_expr(&mut self, start: Span) -> Result<Stmt> {
+        let parameters = self.parse_parameters()?;
+        let body = self.parse_function_body("Expected function body following parameters")?;
+
+        let function = StmtKind::FnExpr {
+            parameters: parameters.into(),
+            body: body.into(),
+        };
+        Ok(self.node(function, start))
+    }
+
+    fn parse_function_body(&mut self, err: &str) -> Result<Vec<Stmt>> {
+        self.function_depth += 1;
+        let body = self.parse_block(err);
+        self.function_depth -= 1;
+        body
+    }
+
+    /// (a, b = 10)
+    fn parse_parameters(&mut self) -> Result<Vec<Parameter>> {
+        self.expect(TokenType::LeftParen, "Expected open parenthesis")?;
+
+        let mut parameters: Vec<Parameter> = vec![];
+        while let Some(t) = self.peek() {
+            if t.token_type == TokenType::RightParen {
+                break;
+            }
+            if !parameters.is_empty() {
+                self.expect(TokenType::Comma, "Expected comma between parameters")?;
+            }
+
+            let rest = self.peek().map(|t| t.token_type) == Some(TokenType::Ellipsis);
+            if rest {
+                self.eat();
+            }
+
+            let parameter = self.parse_primary_expr()?;
+            let StmtKind::Identifier(name) = parameter.kind else {
+                return Err(anyhow!(ParseError::ExpectedParameterToBeString(
+                    parameter.kind.name()
+                )))
+                .with_span(parameter.span);
+            };
+
+            if rest {
+                if let Some(t) = self
+                    .peek()
+                    .filter(|t| t.token_type != TokenType::RightParen)
+                {
+                    return Err(anyhow!(ParseError::RestParameterNotLast(name))).with_span(t.span);
+                }
+                parameters.push(Parameter {
+                    name,
+                    default: None,
+                    rest,
+                });
+                break;
+            }
 
-        let mut parameters = vec![];
-        for arg in args {
-            if let Stmt::Identifier(v) = arg {
-                parameters.push(v);
-            } else {
-                return Err(anyhow!(ParseError::ExpectedParameterToBeString(arg)));
+            let mut default = None;
+            if let Some(TokenType::Equals) = self.peek().map(|t| t.token_type) {
+                self.eat();
+                default = Some(self.parse_expr()?);
+            } else if parameters.iter().any(|p| p.default.is_some()) {
+                return Err(anyhow!(ParseError::RequiredParameterAfterDefault(name)))
+                    .with_span(parameter.span);
             }
+
+            parameters.push(Parameter {
+                name,
+                default,
+                rest,
+            });
         }
 
         self.expect(
-            TokenType::LeftBrace,
-            "Expected function body following declaration",
-        );
+            TokenType::RightParen,
+            "Missing closing parenthesis in parameter list",
+        )?;
+        Ok(parameters)
+    }
+
+    /// if a { ... } else if b { ... } else { ... }
+    ///   ^ the `if` keyword is already consumed
+    fn parse_if_expr(&mut self, start: Span) -> Result<Stmt> {
+        let condition = self.parse_expr()?;
+        let then_branch = self.parse_block("Expected block after if condition")?;
+
+        let mut else_branch = None;
+        if let Some(TokenType::ElseKeyword) = self.peek().map(|t| t.token_type) {
+            self.eat();
+            let branch_start = self.start();
+            let branch = match self.peek().map(|t| t.token_type) {
+                Some(TokenType::IfKeyword) => {
+                    self.eat();
+                    self.parse_if_expr(branch_start)?
+                }
+                _ => {
+                    let body = self.parse_block("Expected block or if after else")?;
+                    self.node(StmtKind::Block(body), branch_start)
+                }
+            };
+            else_branch = Some(Box::new(branch));
+        }
+
+        Ok(self.node(
+            StmtKind::IfExpr {
+                condition: Box::new(condition),
+                then_branch,
+                else_branch,
+            },
+            start,
+        ))
+    }
+
+    /// while a { ... }
+    ///   ^ the `while` keyword is already consumed
+    fn parse_while_expr(&mut self, start: Span) -> Result<Stmt> {
+        let condition = self.parse_expr()?;
+        let body = self.parse_block("Expected block after while condition")?;
+
+        Ok(self.node(
+            StmtKind::WhileLoop {
+                condition: Box::new(condition),
+                body,
+            },
+            start,
+        ))
+    }
+
+    /// for item in items { ... }
+    ///   ^ the `for` keyword is already consumed
+    fn parse_for_expr(&mut self, start: Span) -> Result<Stmt> {
+        let variable = self
+            .expect(TokenType::Identifier, "Expected loop variable after for")?
+            .value;
+        self.expect(TokenType::InKeyword, "Expected in after loop variable")?;
+        let iterable = self.parse_expr()?;
+        let body = self.parse_block("Expected block after for loop")?;
+
+        Ok(self.node(
+            StmtKind::ForLoop {
+                variable,
+                iterable: Box::new(iterable),
+                body,
+            },
+            start,
+        ))
+    }
+
+    /// [a, b, c]
+    /// ^ the opening bracket is already consumed
+    fn parse_array_literal(&mut self, start: Span) -> Result<Stmt> {
+        let elements = match self.peek() {
+            Some(t) if t.token_type == TokenType::RightBracket => vec![],
+            _ => self.parse_args_list()?,
+        };
+        self.expect(
+            TokenType::RightBracket,
+            "Array literal is missing a closing bracket",
+        )?;
+
+        Ok(self.node(StmtKind::ArrayLiteral(elements), start))
+    }
+
+    /// break, break value, return, return value
+    fn parse_jump_stmt(&mut self) -> Result<Stmt> {
+        let start = self.start();
+        let keyword = self.eat().map(|t| t.token_type);
+
+        let value = match self.peek().map(|t| t.token_type) {
+            Some(TokenType::Semicolon | TokenType::RightBrace | TokenType::Eof) | None => None,
+            _ => Some(Box::new(self.parse_expr()?)),
+        };
+
+        let kind = match keyword {
+            Some(TokenType::ReturnKeyword) => StmtKind::Return(value),
+            _ => StmtKind::Break(value),
+        };
+        let stmt = self.node(kind, start);
+
+        if let StmtKind::Return(_) = stmt.kind {
+            if self.function_depth == 0 {
+                return Err(anyhow!(ParseError::ReturnOutsideFunction)).with_span(stmt.span);
+            }
+        }
+        Ok(stmt)
+    }
+
+    /// { stmt; stmt; expr }
+    fn parse_block(&mut self, err: &str) -> Result<Vec<Stmt>> {
+        self.expect(TokenType::LeftBrace, err)?;
 
         let mut body = vec![];
         while let Some(t) = self.peek() {
@@ -217,15 +698,8 @@ impl Parser {
             body.push(self.parse_stmt()?);
         }
 
-        self.expect(TokenType::RightBrace, "Closing bracket expected");
-
-        let function = Stmt::FnDeclaration {
-            name,
-            parameters,
-            body,
-            is_const: false,
-        };
-        Ok(function)
+        self.expect(TokenType::RightBrace, "Closing bracket expected")?;
+        Ok(body)
     }
 
     fn peek(&mut self) -> Option<&Token> {
@@ -233,7 +707,9 @@ impl Parser {
     }
 
     fn eat(&mut self) -> Option<Token> {
-        self.tokens.next()
+        let token = self.tokens.next()?;
+        self.previous = token.span;
+        Some(token)
     }
 
     fn expect(&mut self, token_type: TokenType, err: &str) -> Result<Token> {
@@ -243,19 +719,32 @@ impl Parser {
                     token_type,
                     t.token_type,
                     err.to_owned()
-                )));
+                )))
+                .with_span(t.span);
             }
             Ok(t)
         } else {
-            Err(anyhow!(ParseError::ExpectedToken))
+            Err(anyhow!(ParseError::ExpectedToken)).with_span(self.previous)
         }
     }
 
+    /// Span of the next token, marking the start of the node about to be parsed.
+    fn start(&mut self) -> Span {
+        let previous = self.previous;
+        self.peek().map_or(previous, |t| t.span)
+    }
+
+    /// Creates a node spanning from `start` up to the last consumed token.
+    fn node(&self, kind: StmtKind, start: Span) -> Stmt {
+        Stmt::new(kind, start.to(self.previous))
+    }
+
     /// { foo: foo, bar, baz: null }
     fn parse_object_expr(&mut self) -> Result<Stmt> {
+        let start = self.start();
         if let Some(t) = self.peek() {
             if t.token_type != TokenType::LeftBrace {
-                return self.parse_additive_expr();
+                return self.parse_range_expr();
             }
 
             self.eat();
@@ -285,7 +774,7 @@ impl Parser {
                     self.expect(
                         TokenType::Colon,
                         "Missing colon after identifier in object expression",
-                    );
+                    )?;
 
                     let value = self.parse_expr()?;
                     properties.push(Property {
@@ -298,7 +787,7 @@ impl Parser {
                             self.expect(
                                 TokenType::Comma,
                                 "Expected comma or closing bracket after property",
-                            );
+                            )?;
                         }
                     }
                 }
@@ -306,52 +795,59 @@ impl Parser {
             self.expect(
                 TokenType::RightBrace,
                 "Object literal is missing a closing brace",
-            );
+            )?;
 
-            return Ok(Stmt::ObjectLiteral(properties));
+            return Ok(self.node(StmtKind::ObjectLiteral(properties), start));
         }
 
-        Err(anyhow!(ParseError::ExpectedToken))
+        Err(anyhow!(ParseError::ExpectedToken)).with_span(self.previous)
     }
 
+    /// a.b, a[b] and a(b) in any order, `o.f(1).g`, `f()[0]`, `adder(5)(10)`
     fn parse_call_member_expr(&mut self) -> Result<Stmt> {
-        let member = self.parse_member_expr()?;
-
-        if let Some(t) = self.peek() {
-            if t.token_type == TokenType::LeftParen {
-                return self.parse_call_expr(member);
-            }
+        let start = self.start();
+        let mut expr = self.parse_primary_expr()?;
+
+        loop {
+            let kind = match self.peek().map(|t| t.token_type) {
+                Some(TokenType::LeftParen) => StmtKind::CallExpr {
+                    args: self.parse_args()?,
+                    caller: Box::new(expr),
+                },
+                Some(TokenType::Dot | TokenType::LeftBracket) => self.parse_member(expr)?,
+                _ => break,
+            };
+            expr = self.node(kind, start);
         }
 
-        Ok(member)
-    }
-
-    /// foo(...args)
-    /// ^..........^
-    fn parse_call_expr(&mut self, caller: Stmt) -> Result<Stmt> {
-        let mut call_expr = Stmt::CallExpr {
-            caller: Box::new(caller),
-            args: self.parse_args()?,
-        };
-
-        if let Some(t) = self.peek() {
-            if t.token_type == TokenType::LeftParen {
-                call_expr = self.parse_call_expr(call_expr)?;
-            }
-        }
-        Ok(call_expr)
+        Ok(expr)
     }
 
     /// foo(...args)
     ///     ^.....^
-    fn parse_args(&mut self) -> Result<Vec<Stmt>> {
-        self.expect(TokenType::LeftParen, "Expected open parenthesis");
+    fn parse_args(&mut self) -> Result<Vec<Argument>> {
+        self.expect(TokenType::LeftParen, "Expected open parenthesis")?;
         if let Some(t) = self.peek() {
-            let args = if t.token_type == TokenType::RightParen {
-                vec![] // args list is empty
-            } else {
-                self.parse_args_list()?
-            };
+            let mut args = vec![];
+            if t.token_type != TokenType::RightParen {
+                args.push(self.parse_argument()?);
+
+                while let Some(t) = self.peek() {
+                    if t.token_type != TokenType::Comma || self.eat().is_none() {
+                        break;
+                    }
+
+                    let start = self.start();
+                    let arg = self.parse_argument()?;
+                    if matches!(args.last(), Some(Argument::Named(..)))
+                        && !matches!(arg, Argument::Named(..))
+                    {
+                        return Err(anyhow!(ParseError::PositionalAfterNamedArgument))
+                            .with_span(start.to(self.previous));
+                    }
+                    args.push(arg);
+                }
+            }
 
             // TODO for nested function parse args takes both parens
             self.expect(
@@ -362,85 +858,129 @@ impl Parser {
             return Ok(args);
         };
 
-        Err(anyhow!(ParseError::ExpectedToken))
+        Err(anyhow!(ParseError::ExpectedToken)).with_span(self.previous)
     }
 
-    fn parse_args_list(&mut self) -> Result<Vec<Stmt>> {
-        let mut args = vec![self.parse_assignment_expr()?];
+    /// x, ...xs, name: x
+    fn parse_argument(&mut self) -> Result<Argument> {
+        if let Some(TokenType::Ellipsis) = self.peek().map(|t| t.token_type) {
+            self.eat();
+            return Ok(Argument::Spread(self.parse_assignment_expr()?));
+        }
 
-        while let Some(t) = self.peek() {
-            if t.token_type != TokenType::Comma || self.eat().is_none() {
-                break;
+        let bare = self.peek().map(|t| t.token_type) == Some(TokenType::Identifier);
+        let value = self.parse_argument_value()?;
+        // A colon cannot continue an expression, so a lone identifier
+        // followed by one is the name of the argument, `(a): 1` is not
+        if let (true, StmtKind::Identifier(name)) = (bare, &value.kind) {
+            if let Some(TokenType::Colon) = self.peek().map(|t| t.token_type) {
+                self.eat();
+                return Ok(Argument::Named(name.clone(), self.parse_argument_value()?));
             }
+        }
 
-            args.push(self.parse_assignment_expr()?);
+        Ok(Argument::Positional(value))
+    }
+
+    fn parse_argument_value(&mut self) -> Result<Stmt> {
+        if self.at_reference() {
+            return self.parse_reference(&[TokenType::Comma, TokenType::RightParen]);
         }
 
-        Ok(args)
+        self.parse_assignment_expr()
+    }
+
+    fn at_reference(&mut self) -> bool {
+        self.peek().map(|t| t.token_type) == Some(TokenType::At)
+    }
+
+    /// @a, which has to make up a whole argument, `let` initialiser or
+    /// assignment target, so it must be followed by one of `followers`
+    fn parse_reference(&mut self, followers: &[TokenType]) -> Result<Stmt> {
+        let start = self.start();
+        self.eat();
+        let name = self
+            .expect(TokenType::Identifier, "Expected a variable name after @")?
+            .value;
+        let reference = self.node(StmtKind::Reference(name.clone()), start);
+
+        match self.peek().map(|t| t.token_type) {
+            Some(t) if followers.contains(&t) => Ok(reference),
+            _ => Err(anyhow!(ParseError::MisplacedReference(name))).with_span(reference.span),
+        }
     }
 
-    fn parse_member_expr(&mut self) -> Result<Stmt> {
-        let mut object = self.parse_primary_expr()?;
+    fn parse_args_list(&mut self) -> Result<Vec<Stmt>> {
+        let mut args = vec![self.parse_assignment_expr()?];
 
         while let Some(t) = self.peek() {
-            if t.token_type != TokenType::Dot && t.token_type != TokenType::LeftBracket {
+            if t.token_type != TokenType::Comma || self.eat().is_none() {
                 break;
             }
 
-            if let Some(operator) = self.eat() {
-                let computed;
-                let property;
+            args.push(self.parse_assignment_expr()?);
+        }
 
-                if operator.token_type == TokenType::Dot {
-                    property = self.parse_primary_expr()?;
+        Ok(args)
+    }
 
-                    if let Stmt::Identifier(_) = property {
-                        continue;
-                    } else {
-                        return Err(anyhow!(ParseError::NoDotOperatorWithoutRhsIdentifier));
-                    }
-                } else {
-                    computed = true;
-                    property = self.parse_expr()?;
-                    self.expect(
-                        TokenType::RightBracket,
-                        "Missing closing bracket in computed member expression",
-                    )?;
-                }
+    /// .b or [b]
+    fn parse_member(&mut self, object: Stmt) -> Result<StmtKind> {
+        let computed = self.eat().map(|t| t.token_type) == Some(TokenType::LeftBracket);
 
-                object = Stmt::MemberExpr {
-                    object: Box::new(object),
-                    property: Box::new(property),
-                    computed,
-                };
+        let property = if computed {
+            let property = self.parse_expr()?;
+            self.expect(
+                TokenType::RightBracket,
+                "Missing closing bracket in computed member expression",
+            )?;
+            property
+        } else {
+            let property = self.parse_primary_expr()?;
+            if !matches!(property.kind, StmtKind::Identifier(_)) {
+                return Err(anyhow!(ParseError::NoDotOperatorWithoutRhsIdentifier))
+                    .with_span(property.span);
             }
-        }
+            property
+        };
 
-        Ok(object)
+        Ok(StmtKind::MemberExpr {
+            object: Box::new(object),
+            property: Box::new(property),
+            computed,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::Stmt::{AssignmentExpr, FnDeclaration};
+    use crate::error::SpannedError;
+    use crate::parser::StmtKind::{AssignmentExpr, FnDeclaration};
 
     #[test]
     fn basic() {
         let expected = Program {
-            body: vec![Stmt::BinaryExpr {
-                left: Box::new(Stmt::NumericLiteral("45".to_owned())),
-                right: Box::new(Stmt::BinaryExpr {
-                    left: Box::new(Stmt::BinaryExpr {
-                        left: Box::new(Stmt::Identifier("foo".to_owned())),
-                        right: Box::new(Stmt::NumericLiteral("4".to_owned())),
-                        operator: "+".to_owned(),
-                    }),
-                    right: Box::new(Stmt::Identifier("bar".to_owned())),
-                    operator: "%".to_owned(),
-                }),
+            body: vec![StmtKind::BinaryExpr {
+                left: Box::new(StmtKind::IntegerLiteral(45).into()),
+                right: Box::new(
+                    StmtKind::BinaryExpr {
+                        left: Box::new(
+                            StmtKind::BinaryExpr {
+                                left: Box::new(StmtKind::Identifier("foo".to_owned()).into()),
+                                right: Box::new(StmtKind::IntegerLiteral(4).into()),
+                                operator: "+".to_owned(),
+                            }
+                            .into(),
+                        ),
+                        right: Box::new(StmtKind::Identifier("bar".to_owned()).into()),
+                        operator: "%".to_owned(),
+                    }
+                    .into(),
+                ),
                 operator: "+".to_owned(),
-            }],
+            }
+            .into()],
         };
         let input = "45 + (foo + 4) % bar";
         let mut parser = Parser::new();
@@ -455,40 +995,53 @@ mod tests {
     fn assignment() {
         let expected = Program {
             body: vec![
-                Stmt::VarDeclaration {
+                StmtKind::VarDeclaration {
                     constant: false,
                     identifier: "foo".to_string(),
-                    value: Some(Box::new(Stmt::BinaryExpr {
-                        left: Box::new(Stmt::NumericLiteral("50".to_string())),
-                        right: Box::new(Stmt::NumericLiteral("2".to_string())),
-                        operator: "/".to_string(),
-                    })),
-                },
-                Stmt::VarDeclaration {
+                    value: Some(Box::new(
+                        StmtKind::BinaryExpr {
+                            left: Box::new(StmtKind::IntegerLiteral(50).into()),
+                            right: Box::new(StmtKind::IntegerLiteral(2).into()),
+                            operator: "/".to_string(),
+                        }
+                        .into(),
+                    )),
+                }
+                .into(),
+                StmtKind::VarDeclaration {
                     constant: true,
                     identifier: "bar".to_string(),
-                    value: Some(Box::new(Stmt::ObjectLiteral(vec![
-                        Property {
-                            key: "x".to_string(),
-                            value: Some(Box::new(Stmt::NumericLiteral("100".to_string()))),
-                        },
-                        Property {
-                            key: "y".to_string(),
-                            value: Some(Box::new(Stmt::NumericLiteral("32".to_string()))),
-                        },
-                        Property {
-                            key: "foo".to_string(),
-                            value: None,
-                        },
-                        Property {
-                            key: "baz".to_string(),
-                            value: Some(Box::new(Stmt::ObjectLiteral(vec![Property {
-                                key: "z".to_string(),
-                                value: Some(Box::new(Stmt::Identifier("true".to_string()))),
-                            }]))),
-                        },
-                    ]))),
-                },
+                    value: Some(Box::new(
+                        StmtKind::ObjectLiteral(vec![
+                            Property {
+                                key: "x".to_string(),
+                                value: Some(Box::new(StmtKind::IntegerLiteral(100).into())),
+                            },
+                            Property {
+                                key: "y".to_string(),
+                                value: Some(Box::new(StmtKind::IntegerLiteral(32).into())),
+                            },
+                            Property {
+                                key: "foo".to_string(),
+                                value: None,
+                            },
+                            Property {
+                                key: "baz".to_string(),
+                                value: Some(Box::new(
+                                    StmtKind::ObjectLiteral(vec![Property {
+                                        key: "z".to_string(),
+                                        value: Some(Box::new(
+                                            StmtKind::Identifier("true".to_string()).into(),
+                                        )),
+                                    }])
+                                    .into(),
+                                )),
+                            },
+                        ])
+                        .into(),
+                    )),
+                }
+                .into(),
             ],
         };
 
@@ -512,38 +1065,80 @@ mod tests {
         assert_eq!(program, expected);
     }
 
+    #[test]
+    fn malformed_object_and_arguments() {
+        let cases = [
+            ("{ a 1, b: 2 }", "Expected Colon but got Integer", 4),
+            ("{ a: 1 b: 2 }", "Expected Comma but got Identifier", 7),
+            ("{ a: 1, b: 2", "Expected Comma but got Eof", 12),
+            ("f(1 2)", "Expected RightParen but got Integer", 4),
+        ];
+
+        for (src, message, column) in cases {
+            let error = Parser::new().produce_ast(src.to_string()).unwrap_err();
+            assert_eq!(message, error.to_string(), "{src}");
+            let span = error.downcast_ref::<SpannedError>().unwrap().span;
+            assert_eq!(column, span.start, "{src}");
+        }
+    }
+
     #[test]
     fn declare_function() {
         let expected = Program {
             body: vec![FnDeclaration {
                 name: "add".to_owned(),
-                parameters: vec!["x".to_owned(), "y".to_owned()],
+                parameters: vec![
+                    Parameter {
+                        name: "x".to_owned(),
+                        default: None,
+                        rest: false,
+                    },
+                    Parameter {
+                        name: "y".to_owned(),
+                        default: None,
+                        rest: false,
+                    },
+                ]
+                .into(),
                 body: vec![
-                    Stmt::FnDeclaration {
+                    StmtKind::FnDeclaration {
                         name: "subtract".to_owned(),
-                        parameters: vec![],
-                        body: vec![Stmt::CallExpr {
+                        parameters: vec![].into(),
+                        body: vec![StmtKind::CallExpr {
                             args: vec![],
-                            caller: Box::new(Stmt::Identifier("print".to_owned())),
-                        }],
+                            caller: Box::new(StmtKind::Identifier("print".to_owned()).into()),
+                        }
+                        .into()]
+                        .into(),
                         is_const: false,
-                    },
-                    AssignmentExpr {
-                        assignee: Box::new(Stmt::Identifier("result".to_owned())),
-                        value: Box::new(Stmt::BinaryExpr {
-                            left: Box::new(Stmt::Identifier("x".to_owned())),
-                            right: Box::new(Stmt::Identifier("y".to_owned())),
-                            operator: "+".to_owned(),
-                        }),
-                    },
-                    Stmt::CallExpr {
-                        args: vec![Stmt::Identifier("result".to_owned())],
-                        caller: Box::new(Stmt::Identifier("print".to_owned())),
-                    },
-                    Stmt::Identifier("result".to_owned()),
-                ],
+                    }
+                    .into(),
+                    StmtKind::VarDeclaration {
+                        constant: false,
+                        identifier: "result".to_owned(),
+                        value: Some(Box::new(
+                            StmtKind::BinaryExpr {
+                                left: Box::new(StmtKind::Identifier("x".to_owned()).into()),
+                                right: Box::new(StmtKind::Identifier("y".to_owned()).into()),
+                                operator: "+".to_owned(),
+                            }
+                            .into(),
+                        )),
+                    }
+                    .into(),
+                    StmtKind::CallExpr {
+                        args: vec![Argument::Positional(
+                            StmtKind::Identifier("result".to_owned()).into(),
+                        )],
+                        caller: Box::new(StmtKind::Identifier("print".to_owned()).into()),
+                    }
+                    .into(),
+                    StmtKind::Identifier("result".to_owned()).into(),
+                ]
+                .into(),
                 is_const: false,
-            }],
+            }
+            .into()],
         };
 
         let input = r#"
@@ -571,30 +1166,38 @@ mod tests {
     fn comment() {
         let expected = Program {
             body: vec![
-                Stmt::Comment(" this is a comment!".to_owned()),
-                Stmt::VarDeclaration {
+                StmtKind::VarDeclaration {
                     constant: false,
                     identifier: "foo".to_owned(),
-                    value: Some(Box::new(Stmt::BinaryExpr {
-                        left: Box::new(Stmt::NumericLiteral("50".to_owned())),
-                        right: Box::new(Stmt::NumericLiteral("2".to_owned())),
-                        operator: "/".to_owned(),
-                    })),
-                },
-                Stmt::Comment(" this does stuff".to_owned()),
-                Stmt::CallExpr {
-                    args: vec![Stmt::BinaryExpr {
-                        left: Box::new(Stmt::BinaryExpr {
-                            left: Box::new(Stmt::NumericLiteral("40".to_owned())),
-                            right: Box::new(Stmt::NumericLiteral("2".to_owned())),
-                            operator: "*".to_owned(),
-                        }),
-                        right: Box::new(Stmt::Identifier("foo".to_owned())),
-                        operator: "+".to_owned(),
-                    }],
-                    caller: Box::new(Stmt::Identifier("print".to_owned())),
-                },
-                Stmt::Comment(" so does this!".to_owned()),
+                    value: Some(Box::new(
+                        StmtKind::BinaryExpr {
+                            left: Box::new(StmtKind::IntegerLiteral(50).into()),
+                            right: Box::new(StmtKind::IntegerLiteral(2).into()),
+                            operator: "/".to_owned(),
+                        }
+                        .into(),
+                    )),
+                }
+                .into(),
+                StmtKind::CallExpr {
+                    args: vec![Argument::Positional(
+                        StmtKind::BinaryExpr {
+                            left: Box::new(
+                                StmtKind::BinaryExpr {
+                                    left: Box::new(StmtKind::IntegerLiteral(40).into()),
+                                    right: Box::new(StmtKind::IntegerLiteral(2).into()),
+                                    operator: "*".to_owned(),
+                                }
+                                .into(),
+                            ),
+                            right: Box::new(StmtKind::Identifier("foo".to_owned()).into()),
+                            operator: "+".to_owned(),
+                        }
+                        .into(),
+                    )],
+                    caller: Box::new(StmtKind::Identifier("print".to_owned()).into()),
+                }
+                .into(),
             ],
         };
 
@@ -618,27 +1221,38 @@ mod tests {
     fn call_expression() {
         let expected = Program {
             body: vec![
-                Stmt::VarDeclaration {
+                StmtKind::VarDeclaration {
                     constant: false,
                     identifier: "foo".to_string(),
-                    value: Some(Box::new(Stmt::BinaryExpr {
-                        left: Box::new(Stmt::NumericLiteral("50".to_string())),
-                        right: Box::new(Stmt::NumericLiteral("2".to_string())),
-                        operator: "/".to_string(),
-                    })),
-                },
-                Stmt::CallExpr {
-                    args: vec![Stmt::BinaryExpr {
-                        left: Box::new(Stmt::BinaryExpr {
-                            left: Box::new(Stmt::NumericLiteral("40".to_string())),
-                            right: Box::new(Stmt::NumericLiteral("2".to_string())),
-                            operator: "*".to_string(),
-                        }),
-                        right: Box::new(Stmt::Identifier("foo".to_string())),
-                        operator: "+".to_string(),
-                    }],
-                    caller: Box::new(Stmt::Identifier("print".to_string())),
-                },
+                    value: Some(Box::new(
+                        StmtKind::BinaryExpr {
+                            left: Box::new(StmtKind::IntegerLiteral(50).into()),
+                            right: Box::new(StmtKind::IntegerLiteral(2).into()),
+                            operator: "/".to_string(),
+                        }
+                        .into(),
+                    )),
+                }
+                .into(),
+                StmtKind::CallExpr {
+                    args: vec![Argument::Positional(
+                        StmtKind::BinaryExpr {
+                            left: Box::new(
+                                StmtKind::BinaryExpr {
+                                    left: Box::new(StmtKind::IntegerLiteral(40).into()),
+                                    right: Box::new(StmtKind::IntegerLiteral(2).into()),
+                                    operator: "*".to_string(),
+                                }
+                                .into(),
+                            ),
+                            right: Box::new(StmtKind::Identifier("foo".to_string()).into()),
+                            operator: "+".to_string(),
+                        }
+                        .into(),
+                    )],
+                    caller: Box::new(StmtKind::Identifier("print".to_string()).into()),
+                }
+                .into(),
             ],
         };
 
@@ -659,13 +1273,17 @@ mod tests {
     #[test]
     fn nested_call_expression() {
         let expected = Program {
-            body: vec![Stmt::CallExpr {
-                args: vec![Stmt::CallExpr {
-                    args: vec![Stmt::NumericLiteral("5".to_owned())],
-                    caller: Box::new(Stmt::Identifier("print".to_owned())),
-                }],
-                caller: Box::new(Stmt::Identifier("print".to_string())),
-            }],
+            body: vec![StmtKind::CallExpr {
+                args: vec![Argument::Positional(
+                    StmtKind::CallExpr {
+                        args: vec![Argument::Positional(StmtKind::IntegerLiteral(5).into())],
+                        caller: Box::new(StmtKind::Identifier("print".to_owned()).into()),
+                    }
+                    .into(),
+                )],
+                caller: Box::new(StmtKind::Identifier("print".to_string()).into()),
+            }
+            .into()],
         };
 
         let input = r#"print(print(5));"#;
@@ -694,4 +1312,597 @@ mod tests {
         );
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn spans() {
+        let input = "let foo = 1;\nprint(foo + 20);";
+
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast(input.to_string())
+            .expect("Unable to parse");
+
+        assert_eq!(Span::new(0, 11, 1, 1), program.body[0].span);
+        let StmtKind::CallExpr { args, caller } = &program.body[1].kind else {
+            panic!("Expected a call expression");
+        };
+        assert_eq!(Span::new(13, 28, 2, 1), program.body[1].span);
+        assert_eq!(Span::new(13, 18, 2, 1), caller.span);
+        let Argument::Positional(arg) = &args[0] else {
+            panic!("Expected a positional argument");
+        };
+        assert_eq!(Span::new(19, 27, 2, 7), arg.span);
+    }
+
+    #[test]
+    fn error_span() {
+        let input = "let foo = (1 + 2;";
+
+        let mut parser = Parser::new();
+
+        let error = parser.produce_ast(input.to_string()).unwrap_err();
+        let error = error
+            .downcast_ref::<SpannedError>()
+            .expect("Parse errors should carry a span");
+
+        assert_eq!(Span::new(16, 17, 1, 17), error.span);
+    }
+
+    #[test]
+    fn template_literal() {
+        let expected = Program {
+            body: vec![StmtKind::TemplateLiteral(vec![
+                TemplatePart::Literal("hello ".to_owned()),
+                TemplatePart::Expr(StmtKind::Identifier("name".to_owned()).into()),
+                TemplatePart::Literal(", you have ".to_owned()),
+                TemplatePart::Expr(
+                    StmtKind::BinaryExpr {
+                        left: Box::new(StmtKind::Identifier("count".to_owned()).into()),
+                        right: Box::new(StmtKind::IntegerLiteral(1).into()),
+                        operator: "+".to_owned(),
+                    }
+                    .into(),
+                ),
+                TemplatePart::Literal(" items".to_owned()),
+            ])
+            .into()],
+        };
+
+        let input = r#""hello {name}, you have {count + 1} items""#;
+
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast(input.to_string())
+            .expect("Unable to parse");
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn comparison() {
+        fn binary(left: StmtKind, operator: &str, right: StmtKind) -> StmtKind {
+            StmtKind::BinaryExpr {
+                left: Box::new(left.into()),
+                right: Box::new(right.into()),
+                operator: operator.to_owned(),
+            }
+        }
+        let ident = |name: &str| StmtKind::Identifier(name.to_owned());
+
+        let expected = Program {
+            body: vec![StmtKind::AssignmentExpr {
+                assignee: Box::new(ident("x").into()),
+                value: Box::new(
+                    binary(
+                        binary(
+                            binary(ident("a"), "+", StmtKind::IntegerLiteral(1)),
+                            "<",
+                            binary(ident("b"), "*", StmtKind::IntegerLiteral(2)),
+                        ),
+                        "==",
+                        ident("c"),
+                    )
+                    .into(),
+                ),
+            }
+            .into()],
+        };
+
+        let input = "x = a + 1 < b * 2 == c";
+
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast(input.to_string())
+            .expect("Unable to parse");
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn logical() {
+        let ident =
+            |name: &str| -> Box<Stmt> { Box::new(StmtKind::Identifier(name.to_owned()).into()) };
+
+        let expected = Program {
+            body: vec![StmtKind::LogicalExpr {
+                left: ident("a"),
+                right: Box::new(
+                    StmtKind::LogicalExpr {
+                        left: Box::new(
+                            StmtKind::UnaryExpr {
+                                operator: "!".to_owned(),
+                                operand: ident("b"),
+                            }
+                            .into(),
+                        ),
+                        right: Box::new(
+                            StmtKind::BinaryExpr {
+                                left: ident("c"),
+                                right: ident("d"),
+                                operator: "==".to_owned(),
+                            }
+                            .into(),
+                        ),
+                        operator: "&&".to_owned(),
+                    }
+                    .into(),
+                ),
+                operator: "||".to_owned(),
+            }
+            .into()],
+        };
+
+        let input = "a || !b && c == d";
+
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast(input.to_string())
+            .expect("Unable to parse");
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn if_expression() {
+        let ident = |name: &str| -> Stmt { StmtKind::Identifier(name.to_owned()).into() };
+
+        let expected = Program {
+            body: vec![StmtKind::VarDeclaration {
+                constant: false,
+                identifier: "x".to_owned(),
+                value: Some(Box::new(
+                    StmtKind::IfExpr {
+                        condition: Box::new(ident("a")),
+                        then_branch: vec![StmtKind::IntegerLiteral(1).into()],
+                        else_branch: Some(Box::new(
+                            StmtKind::IfExpr {
+                                condition: Box::new(ident("b")),
+                                then_branch: vec![ident("c"), StmtKind::IntegerLiteral(2).into()],
+                                else_branch: Some(Box::new(
+                                    StmtKind::Block(vec![StmtKind::IntegerLiteral(3).into()])
+                                        .into(),
+                                )),
+                            }
+                            .into(),
+                        )),
+                    }
+                    .into(),
+                )),
+            }
+            .into()],
+        };
+
+        let input = "let x = if a { 1 } else if b { c; 2 } else { 3 };";
+
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast(input.to_string())
+            .expect("Unable to parse");
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn statement_terminators() {
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast("if a { b } c; fn f() { 1 } f()".to_string())
+            .expect("Semicolons are optional after blocks and at the end");
+        assert_eq!(4, program.body.len());
+
+        let program = parser
+            .produce_ast("while a { } -1".to_string())
+            .expect("Unable to parse");
+        assert_eq!(2, program.body.len());
+
+        let error = parser
+            .produce_ast("let a = 1 let b = 2".to_string())
+            .unwrap_err();
+        assert_eq!("Expected Semicolon but got LetKeyword", error.to_string());
+        let error = error.downcast_ref::<SpannedError>().unwrap();
+        assert_eq!(Span::new(10, 13, 1, 11), error.span);
+    }
+
+    #[test]
+    fn return_outside_function() {
+        let mut parser = Parser::new();
+
+        for (src, span) in [
+            ("return 1", Span::new(0, 8, 1, 1)),
+            ("if false { return 1 }; print(2)", Span::new(11, 19, 1, 12)),
+            ("fn f() { 1 }; return", Span::new(14, 20, 1, 15)),
+        ] {
+            let error = parser.produce_ast(src.to_string()).unwrap_err();
+            assert_eq!("`return` outside of a function", error.to_string());
+            let error = error.downcast_ref::<SpannedError>().unwrap();
+            assert_eq!(span, error.span, "{src}");
+        }
+
+        for src in [
+            "fn f() { if true { return 1 } }",
+            "let g = fn() { for i in 0..1 { return i } }",
+            "fn f() { let g = fn() { return }; return }",
+        ] {
+            parser.produce_ast(src.to_string()).expect(src);
+        }
+    }
+
+    #[test]
+    fn loops() {
+        let ident = |name: &str| -> Stmt { StmtKind::Identifier(name.to_owned()).into() };
+
+        let expected = Program {
+            body: vec![
+                StmtKind::WhileLoop {
+                    condition: Box::new(ident("a")),
+                    body: vec![StmtKind::Continue.into()],
+                }
+                .into(),
+                StmtKind::VarDeclaration {
+                    constant: false,
+                    identifier: "x".to_owned(),
+                    value: Some(Box::new(
+                        StmtKind::Loop(vec![
+                            StmtKind::Break(None).into(),
+                            StmtKind::Break(Some(Box::new(ident("b")))).into(),
+                        ])
+                        .into(),
+                    )),
+                }
+                .into(),
+            ],
+        };
+
+        let input = "while a { continue } let x = loop { break; break b };";
+
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast(input.to_string())
+            .expect("Unable to parse");
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn fn_expression() {
+        let ident = |name: &str| -> Stmt { StmtKind::Identifier(name.to_owned()).into() };
+        let lambda = StmtKind::FnExpr {
+            parameters: vec![Parameter {
+                name: "x".to_owned(),
+                default: None,
+                rest: false,
+            }]
+            .into(),
+            body: vec![StmtKind::BinaryExpr {
+                left: Box::new(ident("x")),
+                right: Box::new(StmtKind::IntegerLiteral(1).into()),
+                operator: "+".to_owned(),
+            }
+            .into()]
+            .into(),
+        };
+
+        let expected = Program {
+            body: vec![
+                StmtKind::CallExpr {
+                    args: vec![
+                        Argument::Positional(lambda.clone().into()),
+                        Argument::Positional(ident("xs")),
+                    ],
+                    caller: Box::new(ident("map")),
+                }
+                .into(),
+                lambda.into(),
+            ],
+        };
+
+        let input = "map(fn(x) { x + 1 }, xs); fn(x) { x + 1 }";
+
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast(input.to_string())
+            .expect("Unable to parse");
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn parameters() {
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast("fn f(a, b = 1 + 2) { }".to_string())
+            .expect("Unable to parse");
+        let StmtKind::FnDeclaration { parameters, .. } = &program.body[0].kind else {
+            panic!("Expected a function declaration");
+        };
+        assert_eq!(
+            vec![
+                Parameter {
+                    name: "a".to_owned(),
+                    default: None,
+                    rest: false,
+                },
+                Parameter {
+                    name: "b".to_owned(),
+                    default: Some(
+                        StmtKind::BinaryExpr {
+                            left: Box::new(StmtKind::IntegerLiteral(1).into()),
+                            right: Box::new(StmtKind::IntegerLiteral(2).into()),
+                            operator: "+".to_owned(),
+                        }
+                        .into()
+                    ),
+                    rest: false,
+                },
+            ],
+            parameters[..]
+        );
+
+        let error = parser
+            .produce_ast("fn f(a = 1, b) { }".to_string())
+            .unwrap_err();
+        assert_eq!(
+            "Parameter b without a default follows a parameter with one",
+            error.to_string()
+        );
+        let error = error.downcast_ref::<SpannedError>().unwrap();
+        assert_eq!(Span::new(12, 13, 1, 13), error.span);
+
+        let error = parser.produce_ast("fn f(1) { }".to_string()).unwrap_err();
+        assert_eq!(
+            "Expected parameter to be an identifier but got a integer literal",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn rest_parameters_and_spread() {
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast("fn log(level, ...parts) { } log(1, ...xs);".to_string())
+            .expect("Unable to parse");
+        let StmtKind::FnDeclaration { parameters, .. } = &program.body[0].kind else {
+            panic!("Expected a function declaration");
+        };
+        assert_eq!(
+            vec![
+                Parameter {
+                    name: "level".to_owned(),
+                    default: None,
+                    rest: false,
+                },
+                Parameter {
+                    name: "parts".to_owned(),
+                    default: None,
+                    rest: true,
+                },
+            ],
+            parameters[..]
+        );
+        let StmtKind::CallExpr { args, .. } = &program.body[1].kind else {
+            panic!("Expected a call expression");
+        };
+        assert_eq!(
+            &vec![
+                Argument::Positional(StmtKind::IntegerLiteral(1).into()),
+                Argument::Spread(StmtKind::Identifier("xs".to_owned()).into()),
+            ],
+            args
+        );
+
+        for src in ["fn f(...a, b) { }", "fn f(...a = []) { }"] {
+            let error = parser.produce_ast(src.to_string()).unwrap_err();
+            assert_eq!(
+                "Rest parameter a must be the last parameter",
+                error.to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn named_arguments() {
+        let mut parser = Parser::new();
+
+        let program = parser
+            .produce_ast("connect(h, port: 80 + 1)".to_string())
+            .expect("Unable to parse");
+        let StmtKind::CallExpr { args, .. } = &program.body[0].kind else {
+            panic!("Expected a call expression");
+        };
+        assert_eq!(
+            &vec![
+                Argument::Positional(StmtKind::Identifier("h".to_owned()).into()),
+                Argument::Named(
+                    "port".to_owned(),
+                    StmtKind::BinaryExpr {
+                        left: Box::new(StmtKind::IntegerLiteral(80).into()),
+                        right: Box::new(StmtKind::IntegerLiteral(1).into()),
+                        operator: "+".to_owned(),
+                    }
+                    .into()
+                ),
+            ],
+            args
+        );
+
+        let error = parser
+            .produce_ast("connect(port: 80, h)".to_string())
+            .unwrap_err();
+        assert_eq!(
+            "Positional argument follows a named argument",
+            error.to_string()
+        );
+        let error = error.downcast_ref::<SpannedError>().unwrap();
+        assert_eq!(Span::new(18, 19, 1, 19), error.span);
+
+        let error = parser.produce_ast("f((a): 1)".to_string()).unwrap_err();
+        assert_eq!("Expected RightParen but got Colon", error.to_string());
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let expected = Program {
+            body: vec![StmtKind::CompoundAssignment {
+                assignee: Box::new(StmtKind::Identifier("a".to_owned()).into()),
+                value: Box::new(
+                    StmtKind::CompoundAssignment {
+                        assignee: Box::new(StmtKind::Identifier("b".to_owned()).into()),
+                        value: Box::new(StmtKind::IntegerLiteral(2).into()),
+                        operator: "*".to_owned(),
+                    }
+                    .into(),
+                ),
+                operator: "+".to_owned(),
+            }
+            .into()],
+        };
+
+        let mut parser = Parser::new();
+        let program = parser
+            .produce_ast("a += b *= 2;".to_string())
+            .expect("Unable to parse");
+        assert_eq!(expected, program);
+    }
+
+    #[test]
+    fn reference() {
+        let expected = Program {
+            body: vec![
+                StmtKind::CompoundAssignment {
+                    assignee: Box::new(StmtKind::Reference("y".to_owned()).into()),
+                    value: Box::new(StmtKind::IntegerLiteral(4).into()),
+                    operator: "+".to_owned(),
+                }
+                .into(),
+                StmtKind::CallExpr {
+                    args: vec![Argument::Positional(
+                        StmtKind::Reference("y".to_owned()).into(),
+                    )],
+                    caller: Box::new(StmtKind::Identifier("do_print".to_owned()).into()),
+                }
+                .into(),
+            ],
+        };
+
+        let mut parser = Parser::new();
+        let program = parser
+            .produce_ast("@y += 4;\ndo_print(@y)".to_string())
+            .expect("Unable to parse");
+        assert_eq!(expected, program);
+        let StmtKind::CompoundAssignment { assignee, .. } = &program.body[0].kind else {
+            panic!("Expected a compound assignment");
+        };
+        assert_eq!(Span::new(0, 2, 1, 1), assignee.span);
+
+        let error = parser.produce_ast("@1".to_string()).unwrap_err();
+        assert_eq!("Expected Identifier but got Integer", error.to_string());
+
+        for src in [
+            "let r = @y + 1;",
+            "const r = @y;",
+            "print(@y + 1)",
+            "if @y == 1 {}",
+            "let o = { r: @y };",
+            "[@y]",
+            "\"v={@y}\"",
+            "f(...@y)",
+            "r = @y;",
+            "@y;",
+        ] {
+            let error = parser.produce_ast(src.to_string()).unwrap_err();
+            assert_eq!(
+                "Reference @y cannot be used here",
+                error.to_string(),
+                "{src}"
+            );
+        }
+        parser
+            .produce_ast("fn f(a, b) {}; let r = @y; f(@y, b: @r); @r = 1".to_string())
+            .expect("Unable to parse");
+    }
+
+    #[test]
+    fn member_expression() {
+        let ident =
+            |name: &str| -> Box<Stmt> { Box::new(StmtKind::Identifier(name.to_owned()).into()) };
+        let expected = Program {
+            body: vec![StmtKind::MemberExpr {
+                object: Box::new(
+                    StmtKind::MemberExpr {
+                        object: ident("a"),
+                        property: ident("b"),
+                        computed: false,
+                    }
+                    .into(),
+                ),
+                property: Box::new(StmtKind::StringLiteral("c".to_owned()).into()),
+                computed: true,
+            }
+            .into()],
+        };
+
+        let mut parser = Parser::new();
+        let program = parser
+            .produce_ast(r#"a.b["c"]"#.to_string())
+            .expect("Unable to parse");
+        assert_eq!(expected, program);
+        assert_eq!(Span::new(0, 8, 1, 1), program.body[0].span);
+
+        let program = parser
+            .produce_ast("o.f(1).g".to_string())
+            .expect("Unable to parse");
+        let expected = StmtKind::MemberExpr {
+            object: Box::new(
+                StmtKind::CallExpr {
+                    args: vec![Argument::Positional(StmtKind::IntegerLiteral(1).into())],
+                    caller: Box::new(
+                        StmtKind::MemberExpr {
+                            object: ident("o"),
+                            property: ident("f"),
+                            computed: false,
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            ),
+            property: ident("g"),
+            computed: false,
+        };
+        assert_eq!(expected, program.body[0].kind);
+
+        for src in ["f().a", "f()[0]", "f(1)(2).a[0](3)"] {
+            parser.produce_ast(src.to_string()).expect(src);
+        }
+
+        let error = parser.produce_ast("a.1".to_string()).unwrap_err();
+        assert_eq!(
+            "Cannot use dot operator without rhs being an identifier",
+            error.to_string()
+        );
+    }
 }
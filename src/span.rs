@@ -0,0 +1,40 @@
+/// A region of source code.
+///
+/// `start` and `end` are byte offsets into the source, `line` and `column`
+/// are the 1-based position of `start` (column counted in characters).
+/// `source` tells apart the inputs of a session, such as the lines entered in
+/// the REPL, so spans of code declared earlier point into the right input.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub source: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self {
+            start,
+            end,
+            line,
+            column,
+            source: 0,
+        }
+    }
+
+    /// Creates a span covering everything from the start of `self` up to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
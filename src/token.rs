@@ -1,29 +1,63 @@
+use crate::span::Span;
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Token {
     pub value: String,
     pub token_type: TokenType,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(value: impl Into<String>, token_type: TokenType) -> Self {
+    pub fn new(value: impl Into<String>, token_type: TokenType, span: Span) -> Self {
         Self {
             value: value.into(),
             token_type,
+            span,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TokenType {
     // foo_bar
     Identifier,
 
     // 0-9
-    Number,
+    Integer,
+
+    // 1.5, 1e9
+    Float,
+
+    // "foo"
+    String,
+
+    // opening " of a string containing interpolations
+    TemplateStart,
+
+    // closing " of a string containing interpolations
+    TemplateEnd,
+
+    // { inside a string
+    InterpolationStart,
+
+    // } closing an interpolation
+    InterpolationEnd,
 
     // + - / * %
     BinaryOperator,
 
+    // += -= /= *= %=
+    CompoundAssignment,
+
+    // == != < <= > >=
+    ComparisonOperator,
+
+    // && ||
+    LogicalOperator,
+
+    // ! ~
+    UnaryOperator,
+
     // Comment
     Comment,
 
@@ -60,6 +94,15 @@ pub enum TokenType {
     // .
     Dot,
 
+    // .. ..=
+    RangeOperator,
+
+    // ...
+    Ellipsis,
+
+    // @
+    At,
+
     // fn
     FnKeyword,
 
@@ -84,6 +127,24 @@ pub enum TokenType {
     // else
     ElseKeyword,
 
+    // while
+    WhileKeyword,
+
+    // loop
+    LoopKeyword,
+
+    // break
+    BreakKeyword,
+
+    // continue
+    ContinueKeyword,
+
+    // for
+    ForKeyword,
+
+    // in
+    InKeyword,
+
     // EOF
     Eof,
 }
@@ -94,6 +155,15 @@ impl TokenType {
             "let" => TokenType::LetKeyword,
             "const" => TokenType::ConstKeyword,
             "fn" => TokenType::FnKeyword,
+            "return" => TokenType::ReturnKeyword,
+            "if" => TokenType::IfKeyword,
+            "else" => TokenType::ElseKeyword,
+            "while" => TokenType::WhileKeyword,
+            "loop" => TokenType::LoopKeyword,
+            "break" => TokenType::BreakKeyword,
+            "continue" => TokenType::ContinueKeyword,
+            "for" => TokenType::ForKeyword,
+            "in" => TokenType::InKeyword,
             _ => {
                 return None;
             }
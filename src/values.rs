@@ -1,53 +1,281 @@
-use std::collections::HashMap;
+use std::{cmp::Ordering, collections::HashMap, rc::Rc};
 
-use crate::{ast::Stmt, environment::Environment};
+use crate::{
+    ast::{Parameter, Stmt},
+    bigint::BigInt,
+    environment::Environment,
+    error::Result,
+};
 
 type FunctionCall = fn(args: Vec<RuntimeValue>, env: &mut Environment) -> RuntimeValue;
 
 #[derive(Debug, PartialEq, Clone)]
+#[allow(unpredictable_function_pointer_comparisons)]
 pub enum RuntimeValue {
     Null,
     Object(HashMap<String, RuntimeValue>),
+    Array(Vec<RuntimeValue>),
+    /// Integers from `start` up to `end`, which is excluded unless `inclusive`.
+    Range {
+        start: i64,
+        end: i64,
+        inclusive: bool,
+    },
     Boolean(bool),
     Function {
         name: String,
-        parameters: Vec<String>,
+        parameters: Rc<[Parameter]>,
         declaration_env: Environment,
-        body: Vec<Stmt>,
+        body: Rc<[Stmt]>,
+    },
+    NativeFn {
+        name: &'static str,
+        call: FunctionCall,
+    },
+    /// `@name`, the binding of `name` in the scope that declares it.
+    ///
+    /// A variable bound to a reference reads and writes its target instead.
+    Reference {
+        name: String,
+        scope: Environment,
     },
-    NativeFn(FunctionCall),
-    Number(String),
+    Integer(i64),
+    BigInt(BigInt),
+    Float(f64),
+    String(String),
+}
+
+impl RuntimeValue {
+    /// The value a reference points to, any other value is returned as is.
+    pub fn dereference(self) -> Result<RuntimeValue> {
+        match self {
+            RuntimeValue::Reference { name, scope } => scope.lookup_var(&name),
+            value => Ok(value),
+        }
+    }
+
+    /// Name of the value's type, used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            RuntimeValue::Null => "null",
+            RuntimeValue::Object(_) => "object",
+            RuntimeValue::Array(_) => "array",
+            RuntimeValue::Range { .. } => "range",
+            RuntimeValue::Boolean(_) => "boolean",
+            RuntimeValue::Function { .. } | RuntimeValue::NativeFn { .. } => "function",
+            RuntimeValue::Reference { .. } => "reference",
+            RuntimeValue::Integer(_) | RuntimeValue::BigInt(_) => "integer",
+            RuntimeValue::Float(_) => "float",
+            RuntimeValue::String(_) => "string",
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(
+            self,
+            RuntimeValue::Integer(_) | RuntimeValue::BigInt(_) | RuntimeValue::Float(_)
+        )
+    }
+
+    /// Integral value as an arbitrary precision integer.
+    pub fn as_big_int(&self) -> Option<BigInt> {
+        match self {
+            RuntimeValue::Integer(i) => Some(BigInt::from(*i)),
+            RuntimeValue::BigInt(i) => Some(i.clone()),
+            _ => None,
+        }
+    }
+
+    /// Numeric value as a float, used when promoting mixed arithmetic.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            RuntimeValue::Integer(i) => Some(*i as f64),
+            RuntimeValue::BigInt(i) => Some(i.to_f64()),
+            RuntimeValue::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Whether the value counts as true in a condition.
+    ///
+    /// `null`, `false`, zero, `NaN` and the empty string are falsy, every other
+    /// value is truthy, including empty objects, arrays and functions.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            RuntimeValue::Null => false,
+            RuntimeValue::Boolean(b) => *b,
+            RuntimeValue::Integer(i) => *i != 0,
+            RuntimeValue::BigInt(i) => !i.is_zero(),
+            RuntimeValue::Float(f) => *f != 0.0 && !f.is_nan(),
+            RuntimeValue::String(s) => !s.is_empty(),
+            RuntimeValue::Object(_)
+            | RuntimeValue::Array(_)
+            | RuntimeValue::Range { .. }
+            | RuntimeValue::Function { .. }
+            | RuntimeValue::NativeFn { .. }
+            | RuntimeValue::Reference { .. } => true,
+        }
+    }
+
+    /// Equality as seen by `==` in the language.
+    ///
+    /// Numbers compare by value across integers and floats, objects and arrays
+    /// compare their contents and functions are equal when they were created
+    /// by the same declaration in the same scope. Values of different types
+    /// are never equal. References are read through, so they never get here.
+    pub fn equals(&self, other: &RuntimeValue) -> bool {
+        match (self, other) {
+            (lhs, rhs) if lhs.is_number() && rhs.is_number() => {
+                lhs.compare(rhs) == Some(Ordering::Equal)
+            }
+            (RuntimeValue::Null, RuntimeValue::Null) => true,
+            (RuntimeValue::Boolean(lhs), RuntimeValue::Boolean(rhs)) => lhs == rhs,
+            (RuntimeValue::String(lhs), RuntimeValue::String(rhs)) => lhs == rhs,
+            (RuntimeValue::Object(lhs), RuntimeValue::Object(rhs)) => {
+                lhs.len() == rhs.len()
+                    && lhs
+                        .iter()
+                        .all(|(k, v)| rhs.get(k).is_some_and(|other| v.equals(other)))
+            }
+            (RuntimeValue::Array(lhs), RuntimeValue::Array(rhs)) => {
+                lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(a, b)| a.equals(b))
+            }
+            (
+                RuntimeValue::Range {
+                    start,
+                    end,
+                    inclusive,
+                },
+                RuntimeValue::Range {
+                    start: other_start,
+                    end: other_end,
+                    inclusive: other_inclusive,
+                },
+            ) => start == other_start && end == other_end && inclusive == other_inclusive,
+            // The body is shared by every function created from a declaration
+            (
+                RuntimeValue::Function {
+                    declaration_env,
+                    body,
+                    ..
+                },
+                RuntimeValue::Function {
+                    declaration_env: other_env,
+                    body: other_body,
+                    ..
+                },
+            ) => Rc::ptr_eq(body, other_body) && declaration_env == other_env,
+            (
+                RuntimeValue::NativeFn { call, .. },
+                RuntimeValue::NativeFn {
+                    call: other_call, ..
+                },
+            ) => std::ptr::fn_addr_eq(*call, *other_call),
+            _ => false,
+        }
+    }
+
+    /// Items visited by a `for` loop: the elements of an array, the integers
+    /// of a range, the keys of an object in sorted order and the characters of
+    /// a string. Other values cannot be iterated.
+    pub fn iterate(self) -> Option<Box<dyn Iterator<Item = RuntimeValue>>> {
+        match self {
+            RuntimeValue::Array(elements) => Some(Box::new(elements.into_iter())),
+            RuntimeValue::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let integers: Box<dyn Iterator<Item = i64>> = if inclusive {
+                    Box::new(start..=end)
+                } else {
+                    Box::new(start..end)
+                };
+                Some(Box::new(integers.map(RuntimeValue::Integer)))
+            }
+            RuntimeValue::Object(properties) => {
+                let mut keys = properties.into_keys().collect::<Vec<_>>();
+                keys.sort();
+                Some(Box::new(keys.into_iter().map(RuntimeValue::String)))
+            }
+            RuntimeValue::String(s) => {
+                let chars = s.chars().map(|c| c.to_string()).collect::<Vec<_>>();
+                Some(Box::new(chars.into_iter().map(RuntimeValue::String)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Ordering used by `<`, `<=`, `>` and `>=`, only numbers and strings are
+    /// ordered. Comparisons involving `NaN` are unordered.
+    pub fn compare(&self, other: &RuntimeValue) -> Option<Ordering> {
+        match (self, other) {
+            (RuntimeValue::Integer(lhs), RuntimeValue::Integer(rhs)) => Some(lhs.cmp(rhs)),
+            (RuntimeValue::String(lhs), RuntimeValue::String(rhs)) => Some(lhs.cmp(rhs)),
+            (lhs, rhs) => match (lhs.as_big_int(), rhs.as_big_int()) {
+                (Some(lhs), Some(rhs)) => Some(lhs.cmp(&rhs)),
+                _ => lhs.as_float()?.partial_cmp(&rhs.as_float()?),
+            },
+        }
+    }
+}
+
+impl From<BigInt> for RuntimeValue {
+    /// Keeps integers that fit in an `i64` as plain integers.
+    fn from(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(i) => RuntimeValue::Integer(i),
+            None => RuntimeValue::BigInt(value),
+        }
+    }
 }
 
 impl std::fmt::Display for RuntimeValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            RuntimeValue::Null => {
-                writeln!(f, "null");
-            }
+            RuntimeValue::Null => write!(f, "null"),
             RuntimeValue::Object(map) => {
-                map.iter().for_each(|(k, v)| {
-                    writeln!(f, "{} -> {}", k, v);
-                });
+                let mut properties = map.iter().collect::<Vec<_>>();
+                properties.sort_by_key(|(key, _)| *key);
+
+                write!(f, "{{")?;
+                for (i, (k, v)) in properties.into_iter().enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    write!(f, "{separator}{k}: {v}")?;
+                }
+                if map.is_empty() {
+                    write!(f, "}}")
+                } else {
+                    write!(f, " }}")
+                }
             }
-            RuntimeValue::Boolean(b) => {
-                writeln!(f, "{}", if *b { "true" } else { "false" });
+            RuntimeValue::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { ", " };
+                    write!(f, "{separator}{element}")?;
+                }
+                write!(f, "]")
             }
+            RuntimeValue::Range {
+                start,
+                end,
+                inclusive,
+            } => write!(f, "{start}..{}{end}", if *inclusive { "=" } else { "" }),
+            RuntimeValue::Boolean(b) => write!(f, "{}", if *b { "true" } else { "false" }),
             RuntimeValue::Function {
                 name,
                 parameters: _,
                 declaration_env: _,
                 body: _,
-            } => {
-                writeln!(f, "{}()", name);
-            }
-            RuntimeValue::NativeFn(_call) => {
-                writeln!(f, "FnCall");
-            }
-            RuntimeValue::Number(n) => {
-                writeln!(f, "{n}");
-            }
+            } => write!(f, "{}()", name),
+            RuntimeValue::NativeFn { .. } => write!(f, "FnCall"),
+            RuntimeValue::Reference { name, .. } => write!(f, "@{name}"),
+            RuntimeValue::Integer(i) => write!(f, "{i}"),
+            RuntimeValue::BigInt(i) => write!(f, "{i}"),
+            // Debug keeps the fraction of whole floats, `1.0` instead of `1`
+            RuntimeValue::Float(n) => write!(f, "{n:?}"),
+            RuntimeValue::String(s) => write!(f, "{s}"),
         }
-        Ok(())
     }
 }